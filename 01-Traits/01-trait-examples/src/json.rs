@@ -0,0 +1,427 @@
+// A small JSON parser and writer shared by the Serializable implementations, so each from_json
+// can work with parsed values instead of re-splitting the raw text itself
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    // Fields keep the order they were written in
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    // The value of an object's field; None for missing fields and for anything but an object
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+// Where parsing failed, as a byte offset into the input
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+// Parses a complete JSON document; anything but whitespace after the value is an error
+pub fn parse(s: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser { input: s, pos: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos < s.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError { message: message.to_string(), position: self.pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'"') => self.parse_string().map(Value::String),
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_object(),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("expected a JSON value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Result<Value, ParseError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error(&format!("expected {}", literal)))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while matches!(parser.peek(), Some(b'0'..=b'9')) {
+                parser.pos += 1;
+            }
+            parser.pos > from
+        };
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        // No leading zeros: "0" on its own, or a number starting 1-9
+        if self.peek() == Some(b'0') {
+            self.pos += 1;
+        } else if !digits(self) {
+            return Err(self.error("expected a digit"));
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !digits(self) {
+                return Err(self.error("expected a digit after '.'"));
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                return Err(self.error("expected a digit in the exponent"));
+            }
+        }
+        self.input[start..self.pos]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| ParseError { message: "invalid number".to_string(), position: start })
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.expect(b'"')?;
+        let mut value = String::new();
+        loop {
+            let Some(c) = self.input[self.pos..].chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    value.push(self.parse_escape()?);
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => {
+                    self.pos += c.len_utf8();
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    // Called just after the backslash
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let escape = self.peek().ok_or_else(|| self.error("unterminated escape"))?;
+        self.pos += 1;
+        Ok(match escape {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.parse_hex4()?;
+                // Characters outside the Basic Multilingual Plane come as a surrogate pair
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if !self.input[self.pos..].starts_with("\\u") {
+                        return Err(self.error("expected a low surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("invalid low surrogate"));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error(&format!("unsupported escape \\{}", escape as char)));
+            }
+        })
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let hex = self.input.get(self.pos..self.pos + 4)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected four hex digits"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(hex, 16).expect("checked hex digits"))
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+// Writes a value back out as compact JSON. Numbers that JSON can't represent (NaN, infinities)
+// are written as null.
+pub fn to_json_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, None, 0);
+    out
+}
+
+// Like to_json_string, but with each array item and object field on its own line, indented by
+// two spaces per level
+pub fn to_json_pretty(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, Some(2), 0);
+    out
+}
+
+// Quotes a string for JSON, escaping quotes, backslashes and every control character
+pub fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn write_value(out: &mut String, value: &Value, indent: Option<usize>, depth: usize) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) if n.is_finite() => out.push_str(&n.to_string()),
+        Value::Number(_) => out.push_str("null"),
+        Value::String(s) => out.push_str(&escape_string(s)),
+        Value::Array(items) => write_container(out, ('[', ']'), items, indent, depth, |out, item, depth| {
+            write_value(out, item, indent, depth);
+        }),
+        Value::Object(fields) => write_container(out, ('{', '}'), fields, indent, depth, |out, (key, value), depth| {
+            out.push_str(&escape_string(key));
+            out.push_str(if indent.is_some() { ": " } else { ":" });
+            write_value(out, value, indent, depth);
+        }),
+    }
+}
+
+// Writes the brackets and separators of an array or object, leaving each entry to `write_entry`
+fn write_container<T>(
+    out: &mut String,
+    (open, close): (char, char),
+    entries: &[T],
+    indent: Option<usize>,
+    depth: usize,
+    write_entry: impl Fn(&mut String, &T, usize),
+) {
+    out.push(open);
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(width) = indent {
+            out.push('\n');
+            out.push_str(&" ".repeat(width * (depth + 1)));
+        }
+        write_entry(out, entry, depth + 1);
+    }
+    if let (Some(width), false) = (indent, entries.is_empty()) {
+        out.push('\n');
+        out.push_str(&" ".repeat(width * depth));
+    }
+    out.push(close);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_at(json: &str) -> (String, usize) {
+        let error = parse(json).unwrap_err();
+        (error.message, error.position)
+    }
+
+    #[test]
+    fn parses_nested_objects_and_arrays() {
+        let value = parse(r#" {"team": {"lead": {"age": 41}}, "tags": [1, [true, null], -2.5e1]} "#).unwrap();
+        assert_eq!(value.get("team").and_then(|team| team.get("lead")).and_then(|lead| lead.get("age")),
+                   Some(&Value::Number(41.0)));
+        assert_eq!(value.get("tags"), Some(&Value::Array(vec![
+            Value::Number(1.0),
+            Value::Array(vec![Value::Bool(true), Value::Null]),
+            Value::Number(-25.0),
+        ])));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(parse("[]"), Ok(Value::Array(Vec::new())));
+        assert_eq!(parse("{}"), Ok(Value::Object(Vec::new())));
+    }
+
+    #[test]
+    fn parses_escaped_strings() {
+        let value = parse(r#""quote \" slash \/ tab \t é 😀""#).unwrap();
+        assert_eq!(value.as_str(), Some("quote \" slash / tab \t é 😀"));
+        assert_eq!(parse(&escape_string("line\nbell\u{7}")).unwrap().as_str(), Some("line\nbell\u{7}"));
+    }
+
+    #[test]
+    fn errors_report_their_position() {
+        assert_eq!(error_at(r#"{"a": [1, 2,]}"#), ("expected a JSON value".to_string(), 12));
+        assert_eq!(error_at(r#"{"a" 1}"#), ("expected ':'".to_string(), 5));
+        assert_eq!(error_at(r#""unterminated"#), ("unterminated string".to_string(), 13));
+        assert_eq!(error_at("[1, 2] 3"), ("unexpected trailing characters".to_string(), 7));
+        assert_eq!(error_at("01"), ("unexpected trailing characters".to_string(), 1));
+        assert_eq!(error_at(r#""\x""#), ("unsupported escape \\x".to_string(), 2));
+    }
+
+    #[test]
+    fn writes_compact_and_pretty_json() {
+        let value = parse(r#"{"a": [1, "b"], "c": {}}"#).unwrap();
+        assert_eq!(to_json_string(&value), r#"{"a":[1,"b"],"c":{}}"#);
+        assert_eq!(to_json_pretty(&value), "{\n  \"a\": [\n    1,\n    \"b\"\n  ],\n  \"c\": {}\n}");
+        assert_eq!(to_json_string(&Value::Number(f64::NAN)), "null");
+    }
+
+    #[test]
+    fn escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(escape_string("say \"hi\" \\ bye"), r#""say \"hi\" \\ bye""#);
+        assert_eq!(escape_string("\n\r\t\u{8}\u{c}\u{1}\u{1f}"), r#""\n\r\t\b\f\u0001\u001f""#);
+        // Everything else, including non-ASCII, is written as is
+        assert_eq!(escape_string("é 😀 /"), "\"é 😀 /\"");
+    }
+
+    #[test]
+    fn written_json_parses_back_to_the_same_value() {
+        let value = Value::Object(vec![
+            ("name".to_string(), Value::String("Ann \"A\"\n\\".to_string())),
+            ("scores".to_string(), Value::Array(vec![Value::Number(1.5), Value::Number(-3.0), Value::Number(1e21)])),
+            ("nested".to_string(), Value::Object(vec![("ok".to_string(), Value::Bool(false))])),
+            ("empty".to_string(), Value::Array(Vec::new())),
+            ("none".to_string(), Value::Null),
+        ]);
+        assert_eq!(parse(&to_json_string(&value)), Ok(value.clone()));
+        assert_eq!(parse(&to_json_pretty(&value)), Ok(value));
+    }
+}
@@ -1,52 +1,249 @@
-use std::collections::HashMap;
-use std::fs::OpenOptions;
+mod json;
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, OpenOptions};
 use std::io::Write;
-use std::time::{Duration, SystemTime};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 // 1. Shape Trait
 trait Shape {
     fn area(&self) -> f64;
     fn perimeter(&self) -> f64;
     fn name(&self) -> &str;
+    
+    // perimeter² / area: independent of size, lowest (4π) for a circle
+    fn compactness(&self) -> f64 {
+        self.perimeter().powi(2) / self.area()
+    }
+    
+    // (width, height) of the axis-aligned bounding box. Shapes that don't know better are
+    // treated as a square with the same perimeter.
+    fn bounding_box(&self) -> (f64, f64) {
+        let side = self.perimeter() / 4.0;
+        (side, side)
+    }
 }
 
 struct Circle { radius: f64 }
 struct Rectangle { width: f64, height: f64 }
+struct Triangle { a: f64, b: f64, c: f64 }
 
 impl Shape for Circle {
     fn area(&self) -> f64 { std::f64::consts::PI * self.radius * self.radius }
     fn perimeter(&self) -> f64 { 2.0 * std::f64::consts::PI * self.radius }
     fn name(&self) -> &str { "Circle" }
+    fn bounding_box(&self) -> (f64, f64) { (2.0 * self.radius, 2.0 * self.radius) }
 }
 
 impl Shape for Rectangle {
     fn area(&self) -> f64 { self.width * self.height }
     fn perimeter(&self) -> f64 { 2.0 * (self.width + self.height) }
     fn name(&self) -> &str { "Rectangle" }
+    fn bounding_box(&self) -> (f64, f64) { (self.width, self.height) }
+}
+
+impl Shape for Triangle {
+    // Heron's formula. Sides that can't form a triangle give 0.0 instead of NaN.
+    fn area(&self) -> f64 {
+        let s = self.perimeter() / 2.0;
+        let product = s * (s - self.a) * (s - self.b) * (s - self.c);
+        if product > 0.0 { product.sqrt() } else { 0.0 }
+    }
+    fn perimeter(&self) -> f64 { self.a + self.b + self.c }
+    fn name(&self) -> &str { "Triangle" }
+}
+
+// Uniform resizing, e.g. for zooming. A negative factor scales by its absolute value, since a
+// shape can't have negative dimensions.
+trait Scalable {
+    fn scale(&self, factor: f64) -> Self where Self: Sized;
+}
+
+impl Scalable for Circle {
+    fn scale(&self, factor: f64) -> Self {
+        Circle { radius: self.radius * factor.abs() }
+    }
+}
+
+impl Scalable for Rectangle {
+    fn scale(&self, factor: f64) -> Self {
+        Rectangle { width: self.width * factor.abs(), height: self.height * factor.abs() }
+    }
+}
+
+// Builds shapes by name, e.g. from user input
+type ShapeConstructor = Box<dyn Fn(&[f64]) -> Result<Box<dyn Shape>, String>>;
+
+struct ShapeFactory {
+    constructors: HashMap<String, ShapeConstructor>,
+}
+
+impl ShapeFactory {
+    fn new() -> Self {
+        Self { constructors: HashMap::new() }
+    }
+    
+    fn with_default_shapes() -> Self {
+        let mut factory = Self::new();
+        factory.register("circle", Box::new(|params| match params {
+            [radius] => Ok(Box::new(Circle { radius: *radius }) as Box<dyn Shape>),
+            _ => Err(format!("circle expects 1 parameter (radius), got {}", params.len())),
+        }));
+        factory.register("rectangle", Box::new(|params| match params {
+            [width, height] => Ok(Box::new(Rectangle { width: *width, height: *height }) as Box<dyn Shape>),
+            _ => Err(format!("rectangle expects 2 parameters (width, height), got {}", params.len())),
+        }));
+        factory.register("triangle", Box::new(|params| match params {
+            [a, b, c] => Ok(Box::new(Triangle { a: *a, b: *b, c: *c }) as Box<dyn Shape>),
+            _ => Err(format!("triangle expects 3 parameters (side lengths), got {}", params.len())),
+        }));
+        factory
+    }
+    
+    fn register(&mut self, name: &str, constructor: ShapeConstructor) {
+        self.constructors.insert(name.to_string(), constructor);
+    }
+    
+    fn create(&self, name: &str, params: &[f64]) -> Result<Box<dyn Shape>, String> {
+        let constructor = self.constructors.get(name)
+            .ok_or_else(|| format!("Unknown shape: {}", name))?;
+        constructor(params)
+    }
+}
+
+// Shapes placed on a plane, bucketed into a grid of square cells so a region query only has to
+// look at shapes in the cells the region touches. A shape is positioned by the top-left corner
+// of its bounding box and listed in every cell that box overlaps.
+struct ShapeScene {
+    cell_size: f64,
+    shapes: Vec<((f64, f64), Box<dyn Shape>)>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl ShapeScene {
+    fn new(cell_size: f64) -> Self {
+        Self { cell_size, shapes: Vec::new(), cells: HashMap::new() }
+    }
+    
+    // The range of cells covered by a rectangle, inclusive on both ends
+    fn cell_range(&self, x: f64, y: f64, w: f64, h: f64) -> impl Iterator<Item = (i64, i64)> {
+        let cell = |v: f64| (v / self.cell_size).floor() as i64;
+        let (min_x, max_x, min_y, max_y) = (cell(x), cell(x + w), cell(y), cell(y + h));
+        (min_x..=max_x).flat_map(move |cx| (min_y..=max_y).map(move |cy| (cx, cy)))
+    }
+    
+    fn add(&mut self, x: f64, y: f64, shape: Box<dyn Shape>) {
+        let index = self.shapes.len();
+        let (w, h) = shape.bounding_box();
+        for cell in self.cell_range(x, y, w, h).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(index);
+        }
+        self.shapes.push(((x, y), shape));
+    }
+    
+    // Shapes whose bounding boxes overlap the region (touching edges count), in the order added
+    fn query_region(&self, x: f64, y: f64, w: f64, h: f64) -> Vec<&dyn Shape> {
+        let mut candidates: Vec<usize> = self.cell_range(x, y, w, h)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates.into_iter()
+            .map(|index| &self.shapes[index])
+            .filter(|((sx, sy), shape)| {
+                let (sw, sh) = shape.bounding_box();
+                *sx <= x + w && x <= sx + sw && *sy <= y + h && y <= sy + sh
+            })
+            .map(|(_, shape)| shape.as_ref())
+            .collect()
+    }
+}
+
+// Solid shapes, kept separate from the flat Shape trait
+trait Shape3D {
+    fn volume(&self) -> f64;
+    fn surface_area(&self) -> f64;
+}
+
+struct Sphere { radius: f64 }
+struct Cuboid { width: f64, height: f64, depth: f64 }
+
+impl Shape3D for Sphere {
+    fn volume(&self) -> f64 { 4.0 / 3.0 * std::f64::consts::PI * self.radius.powi(3) }
+    fn surface_area(&self) -> f64 { 4.0 * std::f64::consts::PI * self.radius * self.radius }
+}
+
+impl Shape3D for Cuboid {
+    fn volume(&self) -> f64 { self.width * self.height * self.depth }
+    fn surface_area(&self) -> f64 {
+        2.0 * (self.width * self.height + self.height * self.depth + self.width * self.depth)
+    }
 }
 
 // 2. Drawable Trait
 trait Drawable {
     fn draw(&self);
     fn set_color(&mut self, color: &str);
+    // Opacity goes from 0.0 (invisible) to 1.0 (fully opaque). draw() can only print, so by
+    // default anything visible is drawn as usual and `out` is left alone; override this to write
+    // the faded version to `out` instead.
+    fn draw_with_opacity(&self, _out: &mut dyn Write, opacity: f32) -> std::io::Result<()> {
+        if opacity > 0.0 {
+            self.draw();
+        }
+        Ok(())
+    }
     
     fn render(&self) {
         println!("Rendering...");
         self.draw();
     }
+    
+    // Widgets take the theme's foreground color unless they override this to use another role
+    fn apply_theme(&mut self, theme: &Theme) {
+        self.set_color(&theme.fg);
+    }
+    
+    // One frame of a fade-in: progress 0.0 is invisible and 1.0 is fully drawn
+    fn draw_at_progress(&self, out: &mut dyn Write, progress: f32) -> std::io::Result<()> {
+        self.draw_with_opacity(out, progress.clamp(0.0, 1.0))
+    }
 }
 
+// A shared color scheme, so every widget can be restyled at once
+struct Theme { fg: String, bg: String, accent: String }
+
 struct Button { text: String, color: String }
 struct Image { path: String, color: String }
 
 impl Drawable for Button {
     fn draw(&self) { println!("Drawing button: {} ({})", self.text, self.color); }
     fn set_color(&mut self, color: &str) { self.color = color.to_string(); }
+    // Buttons are what the user acts on, so they stand out in the accent color
+    fn apply_theme(&mut self, theme: &Theme) { self.set_color(&theme.accent); }
+    fn draw_with_opacity(&self, out: &mut dyn Write, opacity: f32) -> std::io::Result<()> {
+        writeln!(out, "Drawing button: {} ({}, {:.0}% opacity)", self.text, self.color, opacity * 100.0)
+    }
 }
 
 impl Drawable for Image {
     fn draw(&self) { println!("Drawing image: {} ({})", self.path, self.color); }
     fn set_color(&mut self, color: &str) { self.color = color.to_string(); }
+    // Images are framed against the page, so they pick up the background color
+    fn apply_theme(&mut self, theme: &Theme) { self.set_color(&theme.bg); }
+    fn draw_with_opacity(&self, out: &mut dyn Write, opacity: f32) -> std::io::Result<()> {
+        writeln!(out, "Drawing image: {} ({}, {:.0}% opacity)", self.path, self.color, opacity * 100.0)
+    }
 }
 
 // 3. Serializable Trait
@@ -54,21 +251,149 @@ trait Serializable {
     fn to_json(&self) -> String;
     fn from_json(json: &str) -> Result<Self, String> where Self: Sized;
     
+    // Builds the value from already parsed JSON, so containers like Vec and Team can hand their
+    // children a piece of the parsed document. The default writes the JSON back out for
+    // from_json; override it to read the Value directly.
+    fn from_value(value: &json::Value) -> Result<Self, String> where Self: Sized {
+        Self::from_json(&json::to_json_string(value))
+    }
+    
     fn to_bytes(&self) -> Vec<u8> {
         self.to_json().into_bytes()
     }
+    
+    // Writes the same bytes as to_json. Override it for large values so they can be written
+    // piece by piece instead of being built up as one String first.
+    fn serialize_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(self.to_json().as_bytes())
+    }
+    
+    // Types can't list their fields generically, so by default the JSON is wrapped in a
+    // <json> element. Override this to produce proper tags.
+    fn to_xml(&self) -> String {
+        format!("<json>{}</json>", escape_xml(&self.to_json()))
+    }
+}
+
+// Escapes text for use between tags (quotes only need escaping inside attributes)
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct User { name: String, age: u32 }
 
 impl Serializable for User {
     fn to_json(&self) -> String {
-        format!(r#"{{"name": "{}", "age": {}}}"#, self.name, self.age)
+        format!(r#"{{"name": {}, "age": {}}}"#, json::escape_string(&self.name), self.age)
+    }
+    
+    fn from_json(json: &str) -> Result<Self, String> {
+        Self::from_value(&parse_json(json)?)
+    }
+    
+    fn from_value(value: &json::Value) -> Result<Self, String> {
+        let field = |name: &str| value.get(name).ok_or_else(|| format!("missing field \"{}\"", name));
+        
+        let name = field("name")?.as_str().ok_or("\"name\" must be a string")?;
+        let age = field("age")?;
+        let age = age.as_f64()
+            .filter(|age| age.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(age))
+            .ok_or_else(|| format!("\"age\" must be a non-negative integer, got {:?}", age))?;
+        Ok(User { name: name.to_string(), age: age as u32 })
+    }
+    
+    fn to_xml(&self) -> String {
+        format!("<user><name>{}</name><age>{}</age></user>", escape_xml(&self.name), self.age)
+    }
+}
+
+// Parses a whole document for a from_json, turning parse errors into the String errors
+// Serializable uses
+fn parse_json(json: &str) -> Result<json::Value, String> {
+    json::parse(json).map_err(|e| e.to_string())
+}
+
+// A Vec serializes as a JSON array of its elements
+impl<T: Serializable> Serializable for Vec<T> {
+    fn to_json(&self) -> String {
+        let items: Vec<String> = self.iter().map(Serializable::to_json).collect();
+        format!("[{}]", items.join(", "))
+    }
+    
+    // Streams one element at a time, so exporting a huge collection never holds more than one
+    // element's JSON in memory
+    fn serialize_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(b"[")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b", ")?;
+            }
+            item.serialize_to(w)?;
+        }
+        w.write_all(b"]")
+    }
+    
+    fn from_json(json: &str) -> Result<Self, String> {
+        Self::from_value(&parse_json(json)?)
+    }
+    
+    fn from_value(value: &json::Value) -> Result<Self, String> {
+        match value {
+            json::Value::Array(items) => items.iter().map(T::from_value).collect(),
+            other => Err(format!("expected a JSON array, got {:?}", other)),
+        }
+    }
+}
+
+// An Option serializes as null when empty and as its value otherwise
+impl<T: Serializable> Serializable for Option<T> {
+    fn to_json(&self) -> String {
+        match self {
+            Some(value) => value.to_json(),
+            None => "null".to_string(),
+        }
+    }
+    
+    fn from_json(json: &str) -> Result<Self, String> {
+        Self::from_value(&parse_json(json)?)
     }
     
-    fn from_json(_json: &str) -> Result<Self, String> {
-        Ok(User { name: "Parsed User".to_string(), age: 25 })
+    fn from_value(value: &json::Value) -> Result<Self, String> {
+        match value {
+            json::Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+// Nested objects compose their children's to_json/from_value
+#[derive(Debug, Clone, PartialEq)]
+struct Team { lead: User, deputy: Option<User>, members: Vec<User> }
+
+impl Serializable for Team {
+    fn to_json(&self) -> String {
+        format!(
+            r#"{{"lead": {}, "deputy": {}, "members": {}}}"#,
+            self.lead.to_json(),
+            self.deputy.to_json(),
+            self.members.to_json(),
+        )
+    }
+    
+    fn from_json(json: &str) -> Result<Self, String> {
+        Self::from_value(&parse_json(json)?)
+    }
+    
+    fn from_value(value: &json::Value) -> Result<Self, String> {
+        let field = |name: &str| value.get(name).ok_or_else(|| format!("missing field \"{}\"", name));
+        Ok(Team {
+            lead: User::from_value(field("lead")?)?,
+            deputy: Option::from_value(field("deputy")?)?,
+            members: Vec::from_value(field("members")?)?,
+        })
     }
 }
 
@@ -81,6 +406,12 @@ trait Validator {
     fn is_valid(&self) -> bool {
         self.validate().is_ok()
     }
+    
+    // Every failing rule; the default only knows about the first one, so override it
+    // to report everything at once
+    fn validate_all(&self) -> Vec<Self::Error> {
+        self.validate().err().into_iter().collect()
+    }
 }
 
 struct Email(String);
@@ -107,6 +438,159 @@ impl Validator for Email {
         }
         Ok(())
     }
+    
+    fn validate_all(&self) -> Vec<Self::Error> {
+        let mut errors = Vec::new();
+        if self.0.is_empty() {
+            errors.push(EmailError::Empty);
+        }
+        if !self.0.contains('@') {
+            errors.push(EmailError::NoAtSymbol);
+        }
+        if !self.0.contains('.') {
+            errors.push(EmailError::InvalidFormat);
+        }
+        errors
+    }
+}
+
+// Runs the first validator, then the second, stopping at the first failure. The error
+// remembers which side failed.
+struct And<A, B>(A, B);
+
+#[derive(Debug)]
+enum AndError<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A: Validator, B: Validator> Validator for And<A, B> {
+    type Error = AndError<A::Error, B::Error>;
+    
+    fn validate(&self) -> Result<(), Self::Error> {
+        self.0.validate().map_err(AndError::First)?;
+        self.1.validate().map_err(AndError::Second)
+    }
+}
+
+trait ValidatorExt: Validator + Sized {
+    fn and<V: Validator>(self, other: V) -> And<Self, V> {
+        And(self, other)
+    }
+}
+
+impl<T: Validator> ValidatorExt for T {}
+
+// Single-rule email checks, for composing with `and`
+struct NonEmpty<'a>(&'a str);
+struct ContainsAt<'a>(&'a str);
+
+impl Validator for NonEmpty<'_> {
+    type Error = EmailError;
+    
+    fn validate(&self) -> Result<(), Self::Error> {
+        if self.0.is_empty() { Err(EmailError::Empty) } else { Ok(()) }
+    }
+}
+
+impl Validator for ContainsAt<'_> {
+    type Error = EmailError;
+    
+    fn validate(&self) -> Result<(), Self::Error> {
+        if self.0.contains('@') { Ok(()) } else { Err(EmailError::NoAtSymbol) }
+    }
+}
+
+// Reusable bounds checks: the length of a string in characters, and a value within [min, max].
+// min == max asks for an exact value; min > max can never pass, so it's rejected up front.
+struct LengthValidator<'a> {
+    value: &'a str,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Debug)]
+enum LengthError {
+    TooShort { min: usize, actual: usize },
+    TooLong { max: usize, actual: usize },
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthError::TooShort { min, actual } => write!(f, "too short: {} < {} characters", actual, min),
+            LengthError::TooLong { max, actual } => write!(f, "too long: {} > {} characters", actual, max),
+        }
+    }
+}
+
+impl<'a> LengthValidator<'a> {
+    fn new(value: &'a str, min: usize, max: usize) -> Result<Self, String> {
+        if min > max {
+            return Err(format!("Empty length range {}..={}", min, max));
+        }
+        Ok(LengthValidator { value, min, max })
+    }
+}
+
+impl Validator for LengthValidator<'_> {
+    type Error = LengthError;
+    
+    fn validate(&self) -> Result<(), Self::Error> {
+        let actual = self.value.chars().count();
+        if actual < self.min {
+            return Err(LengthError::TooShort { min: self.min, actual });
+        }
+        if actual > self.max {
+            return Err(LengthError::TooLong { max: self.max, actual });
+        }
+        Ok(())
+    }
+}
+
+struct RangeValidator<T: PartialOrd> {
+    value: T,
+    min: T,
+    max: T,
+}
+
+#[derive(Debug)]
+enum RangeError<T> {
+    BelowMin { min: T, actual: T },
+    AboveMax { max: T, actual: T },
+}
+
+impl<T: fmt::Display> fmt::Display for RangeError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RangeError::BelowMin { min, actual } => write!(f, "below minimum: {} < {}", actual, min),
+            RangeError::AboveMax { max, actual } => write!(f, "above maximum: {} > {}", actual, max),
+        }
+    }
+}
+
+impl<T: PartialOrd + fmt::Debug> RangeValidator<T> {
+    fn new(value: T, min: T, max: T) -> Result<Self, String> {
+        // Incomparable bounds (NaN) are rejected too
+        if !matches!(min.partial_cmp(&max), Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)) {
+            return Err(format!("Empty range {:?}..={:?}", min, max));
+        }
+        Ok(RangeValidator { value, min, max })
+    }
+}
+
+impl<T: PartialOrd + Clone> Validator for RangeValidator<T> {
+    type Error = RangeError<T>;
+    
+    fn validate(&self) -> Result<(), Self::Error> {
+        if self.value < self.min {
+            return Err(RangeError::BelowMin { min: self.min.clone(), actual: self.value.clone() });
+        }
+        if self.value > self.max {
+            return Err(RangeError::AboveMax { max: self.max.clone(), actual: self.value.clone() });
+        }
+        Ok(())
+    }
 }
 
 // 5. Cache Trait
@@ -121,13 +605,43 @@ trait Cache<K, V> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CacheStats {
+    hits: u64,
+    misses: u64,
+}
+
+impl CacheStats {
+    // Fraction of lookups that were hits, or 0.0 before any lookups
+    fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
 struct MemoryCache<K, V> {
     data: HashMap<K, V>,
+    // Counted from `get`, which only takes &self
+    hits: Cell<u64>,
+    misses: Cell<u64>,
 }
 
 impl<K, V> MemoryCache<K, V> {
     fn new() -> Self {
-        Self { data: HashMap::new() }
+        Self { data: HashMap::new(), hits: Cell::new(0), misses: Cell::new(0) }
+    }
+    
+    fn stats(&self) -> CacheStats {
+        CacheStats { hits: self.hits.get(), misses: self.misses.get() }
+    }
+    
+    fn reset_stats(&mut self) {
+        self.hits.set(0);
+        self.misses.set(0);
+    }
+    
+    fn len(&self) -> usize {
+        self.data.len()
     }
 }
 
@@ -135,36 +649,268 @@ impl<K, V> Cache<K, V> for MemoryCache<K, V>
 where 
     K: std::hash::Hash + Eq,
 {
-    fn get(&self, key: &K) -> Option<&V> { self.data.get(key) }
+    fn get(&self, key: &K) -> Option<&V> {
+        let value = self.data.get(key);
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.set(counter.get() + 1);
+        value
+    }
     fn put(&mut self, key: K, value: V) { self.data.insert(key, value); }
     fn remove(&mut self, key: &K) -> Option<V> { self.data.remove(key) }
     fn clear(&mut self) { self.data.clear(); }
+    
+    // Checking for a key isn't a lookup, so it doesn't count towards the stats
+    fn contains_key(&self, key: &K) -> bool { self.data.contains_key(key) }
+}
+
+// A cache holding at most `capacity` entries. When full, `put` evicts the least recently used
+// entry. `get` takes &self, so each entry's last use is tracked in a Cell.
+struct LruCache<K, V> {
+    capacity: usize,
+    data: HashMap<K, (V, Cell<u64>)>,
+    // Incremented on every use, so a lower stamp means a less recent use
+    clock: Cell<u64>,
+}
+
+impl<K, V> LruCache<K, V> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self { capacity, data: HashMap::with_capacity(capacity), clock: Cell::new(0) }
+    }
+    
+    fn tick(&self) -> u64 {
+        self.clock.set(self.clock.get() + 1);
+        self.clock.get()
+    }
+}
+
+impl<K, V> Cache<K, V> for LruCache<K, V>
+where
+    K: std::hash::Hash + Eq + Clone,
+{
+    fn get(&self, key: &K) -> Option<&V> {
+        let (value, last_used) = self.data.get(key)?;
+        last_used.set(self.tick());
+        Some(value)
+    }
+    
+    fn put(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.data.contains_key(&key) && self.data.len() >= self.capacity {
+            let oldest = self.data.iter()
+                .min_by_key(|(_, (_, last_used))| last_used.get())
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.data.remove(&oldest);
+            }
+        }
+        let stamp = self.tick();
+        self.data.insert(key, (value, Cell::new(stamp)));
+    }
+    
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.data.remove(key).map(|(value, _)| value)
+    }
+    
+    fn clear(&mut self) {
+        self.data.clear();
+    }
+}
+
+// A cache whose entries expire `ttl` after they were put. Expired entries act as if they were
+// absent straight away, but are only dropped by remove or purge_expired.
+// A zero ttl means every entry is expired as soon as it is stored.
+struct TtlCache<K, V> {
+    ttl: Duration,
+    data: HashMap<K, (V, Instant)>,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: std::hash::Hash + Eq,
+{
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, data: HashMap::new() }
+    }
+    
+    fn is_expired(&self, stored_at: Instant) -> bool {
+        stored_at.elapsed() >= self.ttl
+    }
+    
+    // Drops every expired entry and returns how many there were
+    fn purge_expired(&mut self) -> usize {
+        let before = self.data.len();
+        let ttl = self.ttl;
+        self.data.retain(|_, (_, stored_at)| stored_at.elapsed() < ttl);
+        before - self.data.len()
+    }
+}
+
+impl<K, V> Cache<K, V> for TtlCache<K, V>
+where
+    K: std::hash::Hash + Eq,
+{
+    fn get(&self, key: &K) -> Option<&V> {
+        match self.data.get(key) {
+            Some((value, stored_at)) if !self.is_expired(*stored_at) => Some(value),
+            _ => None,
+        }
+    }
+    
+    fn put(&mut self, key: K, value: V) {
+        self.data.insert(key, (value, Instant::now()));
+    }
+    
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, stored_at) = self.data.remove(key)?;
+        if self.is_expired(stored_at) { None } else { Some(value) }
+    }
+    
+    fn clear(&mut self) {
+        self.data.clear();
+    }
+}
+
+// Looks values up in a MemoryCache, fetching them with retries on a miss. Only successful
+// fetches are cached, so a key that failed is fetched again next time.
+struct CachedRetry<K, V, F> {
+    cache: MemoryCache<K, V>,
+    fetch: F,
+    max_attempts: u32,
+    base_delay: Duration,
+    rng: Lcg,
+}
+
+impl<K, V, F> CachedRetry<K, V, F>
+where
+    K: std::hash::Hash + Eq + Clone,
+    F: FnMut(&K) -> Result<V, String>,
+{
+    fn new(fetch: F, max_attempts: u32, base_delay: Duration) -> Self {
+        Self { cache: MemoryCache::new(), fetch, max_attempts, base_delay, rng: Lcg::new(1) }
+    }
+    
+    fn get(&mut self, key: &K) -> Result<&V, String> {
+        if !self.cache.contains_key(key) {
+            let fetch = &mut self.fetch;
+            let value = retry_with_backoff(|| fetch(key), self.max_attempts, self.base_delay, &mut self.rng)?;
+            self.cache.put(key.clone(), value);
+        }
+        Ok(self.cache.get(key).expect("value was just cached"))
+    }
 }
 
 // 6. Logger Trait
 trait Logger {
-    fn log(&self, level: LogLevel, message: &str);
+    // Writes an entry unconditionally; callers go through `log` so filtering applies
+    fn write_entry(&self, level: &LogLevel, message: &str);
+
+    fn enabled(&self, _level: &LogLevel) -> bool { true }
+
+    // Override to pin the clock, e.g. for reproducible output
+    fn timestamp(&self) -> String { now_string() }
+
+    fn format_entry(&self, level: &LogLevel, message: &str) -> String {
+        format!("[{}][{:?}] {}", self.timestamp(), level, message)
+    }
+
+    fn log(&self, level: LogLevel, message: &str) {
+        if self.enabled(&level) {
+            self.write_entry(&level, message);
+        }
+    }
     
     fn info(&self, message: &str) { self.log(LogLevel::Info, message); }
     fn warn(&self, message: &str) { self.log(LogLevel::Warn, message); }
     fn error(&self, message: &str) { self.log(LogLevel::Error, message); }
 }
 
-#[derive(Debug)]
+// Ordered by severity: Info < Warn < Error
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum LogLevel { Info, Warn, Error }
 
+// (year, month, day) of the given number of days since the Unix epoch, using Howard Hinnant's
+// algorithm with eras starting in March
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Formats seconds since the Unix epoch as UTC, e.g. `2024-01-02T03:04:05Z`
+fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60)
+}
+
+// Seconds since the Unix epoch, or 0 if the clock is set before it
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+fn now_string() -> String {
+    format_timestamp(unix_now())
+}
+
 struct ConsoleLogger;
-struct FileLogger { path: String }
+
+// Once `path` would grow past `max_bytes` it's rotated to `path.1`, pushing older files
+// up to `path.<max_files>`; anything beyond that is dropped
+struct FileLogger {
+    path: String,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl FileLogger {
+    fn new(path: &str, max_bytes: u64, max_files: usize) -> Self {
+        FileLogger { path: path.to_string(), max_bytes, max_files }
+    }
+
+    fn rotated_path(&self, index: usize) -> String {
+        format!("{}.{}", self.path, index)
+    }
+
+    fn rotate(&self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
 
 impl Logger for ConsoleLogger {
-    fn log(&self, level: LogLevel, message: &str) {
-        println!("[{:?}] {}", level, message);
+    fn write_entry(&self, level: &LogLevel, message: &str) {
+        println!("{}", self.format_entry(level, message));
     }
 }
 
 impl Logger for FileLogger {
-    fn log(&self, level: LogLevel, message: &str) {
-        let log_line = format!("[{:?}] {}\n", level, message);
+    fn write_entry(&self, level: &LogLevel, message: &str) {
+        let log_line = format!("{}\n", self.format_entry(level, message));
+        // A missing or empty file is never rotated, so a single oversized line still gets written
+        let current_len = fs::metadata(&self.path).map(|meta| meta.len()).unwrap_or(0);
+        if current_len > 0 && current_len + log_line.len() as u64 > self.max_bytes {
+            self.rotate().expect("Unable to rotate log file");
+        }
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
@@ -174,6 +920,42 @@ impl Logger for FileLogger {
     }
 }
 
+// Drops every entry below `threshold` before it reaches the wrapped logger
+struct LevelLogger<L: Logger> {
+    inner: L,
+    threshold: LogLevel,
+}
+
+impl<L: Logger> LevelLogger<L> {
+    fn new(inner: L, threshold: LogLevel) -> Self {
+        LevelLogger { inner, threshold }
+    }
+}
+
+impl<L: Logger> Logger for LevelLogger<L> {
+    fn write_entry(&self, level: &LogLevel, message: &str) {
+        self.inner.write_entry(level, message);
+    }
+
+    fn enabled(&self, level: &LogLevel) -> bool {
+        *level >= self.threshold && self.inner.enabled(level)
+    }
+}
+
+// Logs the error of a Result (if any) and passes the Result through unchanged
+trait ResultLogExt<T, E> {
+    fn log_err(self, logger: &dyn Logger) -> Self;
+}
+
+impl<T, E: std::fmt::Debug> ResultLogExt<T, E> for Result<T, E> {
+    fn log_err(self, logger: &dyn Logger) -> Self {
+        if let Err(e) = &self {
+            logger.error(&format!("{:?}", e));
+        }
+        self
+    }
+}
+
 // 7. Comparable Trait
 trait Comparable<T> {
     fn compare(&self, other: &T) -> std::cmp::Ordering;
@@ -187,6 +969,7 @@ trait Comparable<T> {
     }
 }
 
+#[derive(Clone)]
 struct Student { name: String, grade: f64 }
 
 impl Comparable<Student> for Student {
@@ -195,15 +978,90 @@ impl Comparable<Student> for Student {
     }
 }
 
+// Shapes of the same kind compare by area
+impl Comparable<Circle> for Circle {
+    fn compare(&self, other: &Circle) -> std::cmp::Ordering {
+        self.area().partial_cmp(&other.area()).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+impl Comparable<Rectangle> for Rectangle {
+    fn compare(&self, other: &Rectangle) -> std::cmp::Ordering {
+        self.area().partial_cmp(&other.area()).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+// Keeps the best `cap` items submitted so far, highest first
+struct Leaderboard<T: Comparable<T> + Clone> {
+    cap: usize,
+    entries: Vec<T>,
+}
+
+impl<T: Comparable<T> + Clone> Leaderboard<T> {
+    fn new(cap: usize) -> Self {
+        Self { cap, entries: Vec::with_capacity(cap + 1) }
+    }
+    
+    // Returns false if the item wasn't good enough to make the board
+    fn submit(&mut self, item: T) -> bool {
+        // Insert after any equal entries so earlier submissions keep their place on ties
+        let position = self.entries.iter()
+            .position(|entry| item.is_greater_than(entry))
+            .unwrap_or(self.entries.len());
+        if position >= self.cap {
+            return false;
+        }
+        self.entries.insert(position, item);
+        self.entries.truncate(self.cap);
+        true
+    }
+    
+    fn entries(&self) -> &[T] {
+        &self.entries
+    }
+}
+
 // 8. Configurable Trait
+#[derive(Debug)]
+enum ConfigError {
+    NotFound(String),
+    Io { path: String, message: String },
+    Parse { path: String, line: usize, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(path) => write!(f, "config file not found: {}", path),
+            ConfigError::Io { path, message } => write!(f, "could not read config file {}: {}", path, message),
+            ConfigError::Parse { path, line, message } => write!(f, "{}:{}: {}", path, line, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 trait Configurable {
     fn set_config(&mut self, key: &str, value: String);
     fn get_config(&self, key: &str) -> Option<&String>;
-    fn load_from_file(&mut self, path: &str) -> Result<(), String>;
+    fn load_from_file(&mut self, path: &str) -> Result<(), ConfigError>;
     
     fn get_config_or_default(&self, key: &str, default: &str) -> String {
         self.get_config(key).cloned().unwrap_or_else(|| default.to_string())
     }
+    
+    // Lets environment variables win over file values. A variable named `<PREFIX>_<KEY>` sets
+    // `key`: the prefix is matched exactly (so use uppercase, e.g. `APP`), followed by a single
+    // underscore, and the rest of the name is lowercased. `APP_PORT=9090` sets `port` to "9090",
+    // and `APP_LOG_LEVEL` sets `log_level`.
+    fn apply_env_overrides(&mut self, prefix: &str) {
+        let prefix = format!("{}_", prefix);
+        for (name, value) in std::env::vars() {
+            if let Some(key) = name.strip_prefix(&prefix).filter(|key| !key.is_empty()) {
+                self.set_config(&key.to_lowercase(), value);
+            }
+        }
+    }
 }
 
 struct Application {
@@ -225,10 +1083,37 @@ impl Configurable for Application {
         self.config.get(key)
     }
     
-    fn load_from_file(&mut self, path: &str) -> Result<(), String> {
+    // Reads `key = value` lines, skipping blank lines and `#` comments. Values from the file
+    // replace keys that are already set; nothing is applied if any line is malformed.
+    fn load_from_file(&mut self, path: &str) -> Result<(), ConfigError> {
         println!("Loading config from: {}", path);
-        self.set_config("debug", "true".to_string());
-        self.set_config("port", "8080".to_string());
+        let contents = fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ConfigError::NotFound(path.to_string()),
+            _ => ConfigError::Io { path: path.to_string(), message: e.to_string() },
+        })?;
+        
+        let mut entries = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message: &str| ConfigError::Parse {
+                path: path.to_string(),
+                line: i + 1,
+                message: message.to_string(),
+            };
+            let (key, value) = line.split_once('=').ok_or_else(|| parse_error("expected `key = value`"))?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(parse_error("missing key before `=`"));
+            }
+            entries.push((key.to_string(), value.trim().to_string()));
+        }
+        
+        for (key, value) in entries {
+            self.set_config(&key, value);
+        }
         Ok(())
     }
 }
@@ -244,19 +1129,42 @@ trait Convertible<T> {
 struct Celsius(f64);
 struct Fahrenheit(f64);
 
+// Temperatures below absolute zero can't be converted
 #[derive(Debug)]
 struct ConversionError;
 
+const ABSOLUTE_ZERO_CELSIUS: f64 = -273.15;
+
 impl Convertible<Fahrenheit> for Celsius {
     type Error = ConversionError;
     
     fn convert_to(&self) -> Result<Fahrenheit, Self::Error> {
+        if self.0 < ABSOLUTE_ZERO_CELSIUS {
+            return Err(ConversionError);
+        }
         Ok(Fahrenheit(self.0 * 9.0 / 5.0 + 32.0))
     }
     
     fn convert_from(f: Fahrenheit) -> Result<Self, Self::Error> {
-        Ok(Celsius((f.0 - 32.0) * 5.0 / 9.0))
+        let c = (f.0 - 32.0) * 5.0 / 9.0;
+        if c < ABSOLUTE_ZERO_CELSIUS {
+            return Err(ConversionError);
+        }
+        Ok(Celsius(c))
+    }
+}
+
+// Converts every item, keeping the successes and the failures apart (each in input order)
+fn convert_all<A: Convertible<B>, B>(items: &[A]) -> (Vec<B>, Vec<A::Error>) {
+    let mut converted = Vec::new();
+    let mut errors = Vec::new();
+    for item in items {
+        match item.convert_to() {
+            Ok(value) => converted.push(value),
+            Err(e) => errors.push(e),
+        }
     }
+    (converted, errors)
 }
 
 // 10. Processable Trait
@@ -300,6 +1208,65 @@ impl Processable<i32> for NumberProcessor {
     }
 }
 
+// Object-safe processing: inputs and outputs are type-erased so processors with different
+// Processable types can be stored together, e.g. in a Vec<Box<dyn DynProcessor>>
+trait DynProcessor {
+    fn process_boxed(&self, input: Box<dyn Any>) -> Result<Box<dyn Any>, String>;
+}
+
+// Adapts a Processable<T> into a DynProcessor. T has to be fixed by the adapter because one
+// processor could implement Processable for several input types.
+struct DynAdapter<P, T> {
+    processor: P,
+    input: PhantomData<fn(T)>,
+}
+
+impl<P, T> DynAdapter<P, T> {
+    fn new(processor: P) -> Self {
+        Self { processor, input: PhantomData }
+    }
+}
+
+impl<P, T> DynProcessor for DynAdapter<P, T>
+where
+    P: Processable<T>,
+    P::Output: 'static,
+    P::Error: fmt::Display,
+    T: 'static,
+{
+    fn process_boxed(&self, input: Box<dyn Any>) -> Result<Box<dyn Any>, String> {
+        let input = input.downcast::<T>()
+            .map_err(|_| format!("expected input of type {}", std::any::type_name::<T>()))?;
+        match self.processor.process(*input) {
+            Ok(output) => Ok(Box::new(output)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+// Runs a value through each processor in turn, feeding every output into the next processor
+struct DynPipeline {
+    stages: Vec<Box<dyn DynProcessor>>,
+}
+
+impl DynPipeline {
+    fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+    
+    fn then(mut self, stage: Box<dyn DynProcessor>) -> Self {
+        self.stages.push(stage);
+        self
+    }
+    
+    // Stops at the first stage that fails; later stages never see the input
+    fn run(&self, input: Box<dyn Any>) -> Result<Box<dyn Any>, String> {
+        self.stages.iter().enumerate().try_fold(input, |value, (i, stage)| {
+            stage.process_boxed(value).map_err(|e| format!("stage {} failed: {}", i + 1, e))
+        })
+    }
+}
+
 // 11. Queryable Trait
 trait Queryable<T> {
     fn find_by_id(&self, id: u32) -> Option<&T>;
@@ -309,6 +1276,19 @@ trait Queryable<T> {
     fn count(&self) -> usize {
         self.find_all().len()
     }
+    
+    // All items ordered by `key`; items with equal keys keep their find_all order
+    fn find_sorted_by<K: Ord, F: Fn(&T) -> K>(&self, key: F) -> Vec<&T> {
+        let mut items = self.find_all();
+        items.sort_by_key(|item| key(item));
+        items
+    }
+    
+    fn find_sorted_by_desc<K: Ord, F: Fn(&T) -> K>(&self, key: F) -> Vec<&T> {
+        let mut items = self.find_all();
+        items.sort_by_key(|item| std::cmp::Reverse(key(item)));
+        items
+    }
 }
 
 struct UserRepository {
@@ -365,27 +1345,33 @@ struct Message(String);
 struct SimpleKey(String);
 
 #[derive(Debug)]
-struct CryptoError;
+enum CryptoError {
+    EmptyKey,
+}
+
+// XORs each byte with the key, cycling through every byte of the key. Applying it twice with the
+// same key gives back the original data.
+fn xor_with_key(data: &[u8], key: &SimpleKey) -> Result<Vec<u8>, CryptoError> {
+    let key_bytes = key.0.as_bytes();
+    if key_bytes.is_empty() {
+        return Err(CryptoError::EmptyKey);
+    }
+    Ok(data.iter()
+        .zip(key_bytes.iter().cycle())
+        .map(|(byte, key_byte)| byte ^ key_byte)
+        .collect())
+}
 
 impl Encryptable for Message {
     type Key = SimpleKey;
     type Error = CryptoError;
     
     fn encrypt(&self, key: &Self::Key) -> Result<Vec<u8>, Self::Error> {
-        let mut result = self.0.as_bytes().to_vec();
-        let key_byte = key.0.as_bytes().get(0).unwrap_or(&0);
-        for byte in &mut result {
-            *byte ^= key_byte;
-        }
-        Ok(result)
+        xor_with_key(self.0.as_bytes(), key)
     }
     
     fn decrypt(data: &[u8], key: &Self::Key) -> Result<Self, Self::Error> {
-        let mut result = data.to_vec();
-        let key_byte = key.0.as_bytes().get(0).unwrap_or(&0);
-        for byte in &mut result {
-            *byte ^= key_byte;
-        }
+        let result = xor_with_key(data, key)?;
         Ok(Message(String::from_utf8_lossy(&result).to_string()))
     }
 }
@@ -395,23 +1381,98 @@ trait Observable<T> {
     fn notify(&self, data: &T);
 }
 
+type Observer<T> = Box<dyn Fn(&T)>;
+
+// Handles come from a counter that only goes up, so a handle is never reused and
+// unsubscribing with a stale one is a no-op
 struct EventEmitter<T> {
     name: String,
-    _phantom: std::marker::PhantomData<T>,
+    subscribers: Vec<(usize, Observer<T>)>,
+    next_handle: usize,
 }
 
 impl<T> EventEmitter<T> {
     fn new(name: &str) -> Self {
         Self { 
             name: name.to_string(),
-            _phantom: std::marker::PhantomData,
+            subscribers: Vec::new(),
+            next_handle: 0,
         }
     }
+    
+    fn subscribe(&mut self, observer: Observer<T>) -> usize {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.subscribers.push((handle, observer));
+        handle
+    }
+    
+    // Returns false if the handle was already unsubscribed or never existed
+    fn unsubscribe(&mut self, handle: usize) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(existing, _)| *existing != handle);
+        self.subscribers.len() != before
+    }
 }
 
 impl<T: std::fmt::Debug> Observable<T> for EventEmitter<T> {
     fn notify(&self, data: &T) {
-        println!("EventEmitter '{}' notifying: {:?}", self.name, data);
+        println!("EventEmitter '{}' notifying {} subscriber(s): {:?}", self.name, self.subscribers.len(), data);
+        for (_, observer) in &self.subscribers {
+            observer(data);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GameEvent {
+    CoinCollected,
+    EnemyDefeated,
+}
+
+// Unlocked once `event` has happened `target` times
+struct Achievement {
+    name: String,
+    event: GameEvent,
+    target: u32,
+}
+
+// Unlocks achievements as the counts of game events reach their targets. Observers only get
+// `&self`, so a tracker observes events from inside a RefCell.
+struct AchievementTracker {
+    achievements: Vec<Achievement>,
+    counts: HashMap<GameEvent, u32>,
+    unlocked: Vec<String>,
+}
+
+impl AchievementTracker {
+    fn new() -> Self {
+        Self { achievements: Vec::new(), counts: HashMap::new(), unlocked: Vec::new() }
+    }
+    
+    fn with_achievement(mut self, name: &str, event: GameEvent, target: u32) -> Self {
+        self.achievements.push(Achievement { name: name.to_string(), event, target });
+        self
+    }
+    
+    // In the order they were unlocked
+    fn unlocked(&self) -> &[String] {
+        &self.unlocked
+    }
+}
+
+impl Observable<GameEvent> for RefCell<AchievementTracker> {
+    fn notify(&self, event: &GameEvent) {
+        let tracker = &mut *self.borrow_mut();
+        let count = tracker.counts.entry(*event).or_insert(0);
+        *count += 1;
+        // Counts only ever go up, so each achievement hits its target exactly once
+        for achievement in &tracker.achievements {
+            if achievement.event == *event && achievement.target == *count {
+                println!("Achievement unlocked: {}", achievement.name);
+                tracker.unlocked.push(achievement.name.clone());
+            }
+        }
     }
 }
 
@@ -436,6 +1497,14 @@ struct Car {
     year: u32,
 }
 
+// The first production car (the Benz Patent-Motorwagen)
+const FIRST_CAR_YEAR: u32 = 1886;
+
+// The current year in UTC
+fn current_year() -> u32 {
+    civil_date((unix_now() / 86_400) as i64).0 as u32
+}
+
 impl CarBuilder {
     fn new() -> Self {
         Self { make: None, model: None, year: None }
@@ -461,11 +1530,21 @@ impl Buildable for CarBuilder {
     type Output = Result<Car, String>;
     
     fn build(self) -> Self::Output {
-        Ok(Car {
-            make: self.make.ok_or("Make is required")?,
-            model: self.model.ok_or("Model is required")?,
-            year: self.year.ok_or("Year is required")?,
-        })
+        let make = self.make.ok_or("Make is required")?;
+        let model = self.model.ok_or("Model is required")?;
+        let year = self.year.ok_or("Year is required")?;
+        if make.trim().is_empty() {
+            return Err("Make must not be empty".to_string());
+        }
+        if model.trim().is_empty() {
+            return Err("Model must not be empty".to_string());
+        }
+        // Next year's models go on sale before the year starts
+        let latest_year = current_year() + 1;
+        if !(FIRST_CAR_YEAR..=latest_year).contains(&year) {
+            return Err(format!("Year {} is outside {}..={}", year, FIRST_CAR_YEAR, latest_year));
+        }
+        Ok(Car { make, model, year })
     }
     
     fn reset(&mut self) {
@@ -487,27 +1566,357 @@ trait Schedulable {
     }
 }
 
+// Runs its job on a shared Scheduler. Holds the handle of its latest scheduling, so it can be
+// cancelled or checked later; scheduling again replaces (and cancels) the previous one.
 struct Task {
     id: u32,
     name: String,
-    scheduled: bool,
+    scheduler: Rc<Scheduler>,
+    // An Fn rather than FnOnce, since the task can be scheduled more than once
+    job: Arc<dyn Fn() + Send + Sync>,
+    handle: RefCell<Option<ScheduledTask>>,
+}
+
+impl Task {
+    fn new(id: u32, name: &str, scheduler: &Rc<Scheduler>, job: impl Fn() + Send + Sync + 'static) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            scheduler: Rc::clone(scheduler),
+            job: Arc::new(job),
+            handle: RefCell::new(None),
+        }
+    }
 }
 
 impl Schedulable for Task {
     fn schedule(&self, delay: Duration) {
         println!("Task '{}' (ID: {}) scheduled to run in {:?}", self.name, self.id, delay);
+        let job = Arc::clone(&self.job);
+        let handle = self.scheduler.schedule(delay, move || job());
+        if let Some(previous) = self.handle.replace(Some(handle)) {
+            previous.cancel();
+        }
     }
     
+    // A time in the past runs the task straight away
     fn schedule_at(&self, time: SystemTime) {
-        println!("Task '{}' (ID: {}) scheduled to run at {:?}", self.name, self.id, time);
+        let delay = time.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO);
+        self.schedule(delay);
+    }
+    
+    fn cancel(&self) {
+        let cancelled = self.handle.borrow_mut().take().is_some_and(|handle| handle.cancel());
+        if cancelled {
+            println!("Task '{}' (ID: {}) cancelled", self.name, self.id);
+        }
+    }
+    
+    fn is_scheduled(&self) -> bool {
+        self.handle.borrow().as_ref().is_some_and(ScheduledTask::is_scheduled)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskStatus {
+    Completed,
+    // The task panicked
+    Failed,
+    TimedOut,
+    // Not run because the previous run was still going
+    Skipped,
+}
+
+// Runs a task on a worker thread and stops waiting for it after `timeout`. A thread can't be
+// killed, so a timed out task carries on in the background until it finishes by itself.
+fn run_with_timeout<F: FnOnce() + Send + 'static>(task: F, timeout: Duration) -> TaskStatus {
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        task();
+        // Nobody is listening any more if we already timed out
+        let _ = done_tx.send(());
+    });
+    match done_rx.recv_timeout(timeout) {
+        Ok(()) => TaskStatus::Completed,
+        Err(mpsc::RecvTimeoutError::Timeout) => TaskStatus::TimedOut,
+        Err(mpsc::RecvTimeoutError::Disconnected) => TaskStatus::Failed,
+    }
+}
+
+// A task that runs once per cycle with a time limit. A run that overruns is assumed to still be
+// going when the next cycle comes round, so that cycle is skipped.
+struct RepeatingTask {
+    name: String,
+    timeout: Duration,
+    action: Arc<dyn Fn() + Send + Sync>,
+    skip_next: bool,
+}
+
+impl RepeatingTask {
+    fn new<F: Fn() + Send + Sync + 'static>(name: &str, timeout: Duration, action: F) -> Self {
+        Self { name: name.to_string(), timeout, action: Arc::new(action), skip_next: false }
+    }
+    
+    fn run_cycle(&mut self) -> TaskStatus {
+        if self.skip_next {
+            self.skip_next = false;
+            return TaskStatus::Skipped;
+        }
+        let action = Arc::clone(&self.action);
+        let status = run_with_timeout(move || action(), self.timeout);
+        self.skip_next = status == TaskStatus::TimedOut;
+        status
+    }
+}
+
+// Small deterministic pseudo-random number generator (Knuth's MMIX constants), good enough for
+// jitter without pulling in a crate
+struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    
+    fn next_u32(&mut self) -> u32 {
+        self.state = self.state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        // The high bits of an LCG are the most random
+        (self.state >> 32) as u32
+    }
+    
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        self.next_u32() as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+// Exponential backoff with "equal jitter": attempt n (from 0) waits a random time between
+// base * 2^n / 2 and base * 2^n, so delays always grow but retries don't all line up
+fn backoff_delay(attempt: u32, base: Duration, rng: &mut Lcg) -> Duration {
+    let ceiling = base.saturating_mul(2u32.saturating_pow(attempt));
+    let half = ceiling / 2;
+    half + half.mul_f64(rng.next_f64())
+}
+
+// Runs a fallible task until it succeeds, sleeping for a backoff delay after each failure.
+// Gives up with the last error after `max_attempts` tries; with no attempts allowed, the task
+// never runs.
+fn retry_with_backoff<T, F>(mut task: F, max_attempts: u32, base: Duration, rng: &mut Lcg) -> Result<T, String>
+where
+    F: FnMut() -> Result<T, String>,
+{
+    if max_attempts == 0 {
+        return Err("no attempts allowed".to_string());
+    }
+    let mut attempt = 0;
+    loop {
+        match task() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt + 1 >= max_attempts => {
+                return Err(format!("gave up after {} attempts: {}", max_attempts, e));
+            }
+            Err(e) => {
+                let delay = backoff_delay(attempt, base, rng);
+                println!("Attempt {} failed ({}), retrying in {:?}", attempt + 1, e, delay);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+// Runs jobs on a background thread once their delay has passed. The worker keeps a min-heap of
+// fire times and sleeps until the earliest one, waking early whenever a new job arrives.
+type Job = Box<dyn FnOnce() + Send>;
+
+enum SchedulerMessage {
+    Schedule { id: u64, fire_at: Instant, job: Job },
+    Shutdown,
+}
+
+struct Scheduler {
+    sender: mpsc::Sender<SchedulerMessage>,
+    // Ids of jobs that have neither fired nor been cancelled. Firing and cancelling both remove
+    // the id under the lock, so exactly one of them wins.
+    pending: Arc<Mutex<HashSet<u64>>>,
+    next_id: Cell<u64>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+// Lets the caller cancel a scheduled job or check whether it's still waiting to run
+struct ScheduledTask {
+    id: u64,
+    pending: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl ScheduledTask {
+    // Returns false if the job has already fired or been cancelled
+    fn cancel(&self) -> bool {
+        self.pending.lock().expect("scheduler lock poisoned").remove(&self.id)
+    }
+    
+    fn is_scheduled(&self) -> bool {
+        self.pending.lock().expect("scheduler lock poisoned").contains(&self.id)
+    }
+}
+
+impl Scheduler {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let pending = Arc::new(Mutex::new(HashSet::new()));
+        let worker_pending = Arc::clone(&pending);
+        let worker = thread::spawn(move || Self::run_worker(receiver, worker_pending));
+        Self { sender, pending, next_id: Cell::new(0), worker: Some(worker) }
+    }
+    
+    fn schedule(&self, delay: Duration, job: impl FnOnce() + Send + 'static) -> ScheduledTask {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.pending.lock().expect("scheduler lock poisoned").insert(id);
+        let message = SchedulerMessage::Schedule { id, fire_at: Instant::now() + delay, job: Box::new(job) };
+        // The worker only exits once we're dropped, so it's always there to receive this
+        self.sender.send(message).expect("scheduler worker has stopped");
+        ScheduledTask { id, pending: Arc::clone(&self.pending) }
+    }
+    
+    fn run_worker(receiver: mpsc::Receiver<SchedulerMessage>, pending: Arc<Mutex<HashSet<u64>>>) {
+        let mut queue: BinaryHeap<Reverse<(Instant, u64)>> = BinaryHeap::new();
+        let mut jobs: HashMap<u64, Job> = HashMap::new();
+        loop {
+            // Run everything that's due; cancelled jobs are just dropped
+            let now = Instant::now();
+            while let Some(&Reverse((fire_at, id))) = queue.peek() {
+                if fire_at > now {
+                    break;
+                }
+                queue.pop();
+                let job = jobs.remove(&id);
+                let still_pending = pending.lock().expect("scheduler lock poisoned").remove(&id);
+                if let (Some(job), true) = (job, still_pending) {
+                    job();
+                }
+            }
+            
+            let message = match queue.peek() {
+                Some(&Reverse((fire_at, _))) => match receiver.recv_timeout(fire_at.saturating_duration_since(Instant::now())) {
+                    Ok(message) => message,
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                },
+                None => match receiver.recv() {
+                    Ok(message) => message,
+                    Err(_) => return,
+                },
+            };
+            match message {
+                SchedulerMessage::Schedule { id, fire_at, job } => {
+                    queue.push(Reverse((fire_at, id)));
+                    jobs.insert(id, job);
+                }
+                SchedulerMessage::Shutdown => return,
+            }
+        }
+    }
+}
+
+// Stops the worker and waits for it. Jobs that haven't fired yet never run.
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        let _ = self.sender.send(SchedulerMessage::Shutdown);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+// A step-at-a-time alternative to the threaded Scheduler: tasks do a little work each time they're
+// polled and the TaskRunner takes turns between them on the calling thread, so the order things
+// happen in is always the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Poll {
+    Pending,
+    Ready,
+}
+
+trait Cooperative {
+    fn poll_step(&mut self) -> Poll;
+}
+
+struct TaskRunner {
+    tasks: Vec<Box<dyn Cooperative>>,
+}
+
+impl TaskRunner {
+    fn new() -> Self {
+        Self { tasks: Vec::new() }
+    }
+    
+    fn spawn(&mut self, task: impl Cooperative + 'static) {
+        self.tasks.push(Box::new(task));
+    }
+    
+    // Polls each unfinished task once per round until all are Ready; returns how many rounds it took
+    fn run(&mut self) -> usize {
+        let mut rounds = 0;
+        while !self.tasks.is_empty() {
+            rounds += 1;
+            self.tasks.retain_mut(|task| task.poll_step() == Poll::Pending);
+        }
+        rounds
+    }
+}
+
+// Counts down one step per poll, recording each step in a shared log
+struct CountdownTask {
+    name: String,
+    remaining: u32,
+    log: Rc<RefCell<Vec<String>>>,
+}
+
+impl Cooperative for CountdownTask {
+    fn poll_step(&mut self) -> Poll {
+        if self.remaining == 0 {
+            self.log.borrow_mut().push(format!("{} done", self.name));
+            return Poll::Ready;
+        }
+        self.log.borrow_mut().push(format!("{} {}", self.name, self.remaining));
+        self.remaining -= 1;
+        Poll::Pending
+    }
+}
+
+// Profiling helper: counts how often each named operation ran and how long it took in total
+struct Metrics {
+    // In the order each name was first timed
+    operations: Vec<(String, u64, Duration)>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Self { operations: Vec::new() }
     }
     
-    fn cancel(&self) {
-        println!("Task '{}' (ID: {}) cancelled", self.name, self.id);
+    fn time<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        match self.operations.iter_mut().find(|(existing, _, _)| existing == name) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total += elapsed;
+            }
+            None => self.operations.push((name.to_string(), 1, elapsed)),
+        }
+        result
     }
     
-    fn is_scheduled(&self) -> bool {
-        self.scheduled
+    // (name, times run, total time) for each operation
+    fn report(&self) -> Vec<(String, u64, Duration)> {
+        self.operations.clone()
     }
 }
 
@@ -522,10 +1931,53 @@ fn main() {
     let circle = Circle { radius: 5.0 };
     let rectangle = Rectangle { width: 4.0, height: 6.0 };
     
-    println!("{}: Area = {:.2}, Perimeter = {:.2}", 
-             circle.name(), circle.area(), circle.perimeter());
-    println!("{}: Area = {:.2}, Perimeter = {:.2}", 
-             rectangle.name(), rectangle.area(), rectangle.perimeter());
+    println!("{}: Area = {:.2}, Perimeter = {:.2}, Compactness = {:.2}", 
+             circle.name(), circle.area(), circle.perimeter(), circle.compactness());
+    println!("{}: Area = {:.2}, Perimeter = {:.2}, Compactness = {:.2}", 
+             rectangle.name(), rectangle.area(), rectangle.perimeter(), rectangle.compactness());
+    let zoomed_circle = circle.scale(2.0);
+    println!("Circle scaled by 2: Area = {:.2} ({:.1}x)", zoomed_circle.area(), zoomed_circle.area() / circle.area());
+    let zoomed_rectangle = rectangle.scale(-3.0);
+    println!("Rectangle scaled by -3: {} x {}, Area = {:.2} ({:.1}x)",
+             zoomed_rectangle.width, zoomed_rectangle.height, zoomed_rectangle.area(), zoomed_rectangle.area() / rectangle.area());
+    let triangle = Triangle { a: 3.0, b: 4.0, c: 5.0 };
+    println!("{}: Area = {:.2}, Perimeter = {:.2}, Compactness = {:.2}", 
+             triangle.name(), triangle.area(), triangle.perimeter(), triangle.compactness());
+    let degenerate = Triangle { a: 1.0, b: 2.0, c: 10.0 };
+    println!("Degenerate {}: Area = {:.2}", degenerate.name(), degenerate.area());
+    
+    let shapes: Vec<Box<dyn Shape>> = vec![
+        Box::new(Circle { radius: 5.0 }),
+        Box::new(Circle { radius: 0.0 }),
+        Box::new(Rectangle { width: 4.0, height: 6.0 }),
+        Box::new(Triangle { a: 3.0, b: 4.0, c: 5.0 }),
+    ];
+    for shape in &shapes {
+        let (width, height) = shape.bounding_box();
+        println!("{} bounding box: {:.2} x {:.2}", shape.name(), width, height);
+    }
+    
+    let sphere = Sphere { radius: 1.0 };
+    let cuboid = Cuboid { width: 2.0, height: 3.0, depth: 4.0 };
+    println!("Sphere: Volume = {:.2}, Surface area = {:.2}", sphere.volume(), sphere.surface_area());
+    println!("Cuboid: Volume = {:.2}, Surface area = {:.2}", cuboid.volume(), cuboid.surface_area());
+    
+    let factory = ShapeFactory::with_default_shapes();
+    for (name, params) in [("circle", vec![5.0]), ("rectangle", vec![4.0]), ("triangle", vec![3.0, 4.0, 5.0]), ("hexagon", vec![1.0])] {
+        match factory.create(name, &params) {
+            Ok(shape) => println!("Factory built {} with area {:.2}", shape.name(), shape.area()),
+            Err(e) => println!("Factory error: {}", e),
+        }
+    }
+    
+    let mut scene = ShapeScene::new(10.0);
+    scene.add(0.0, 0.0, Box::new(Circle { radius: 2.0 }));
+    scene.add(25.0, 5.0, Box::new(Rectangle { width: 4.0, height: 3.0 }));
+    scene.add(50.0, 50.0, Box::new(Triangle { a: 3.0, b: 4.0, c: 5.0 }));
+    for (x, y, w, h) in [(0.0, 0.0, 30.0, 10.0), (45.0, 45.0, 10.0, 10.0), (10.0, 20.0, 5.0, 5.0)] {
+        let names: Vec<&str> = scene.query_region(x, y, w, h).iter().map(|shape| shape.name()).collect();
+        println!("Shapes in ({}, {}) {}x{}: {:?}", x, y, w, h, names);
+    }
     
     // 2. Drawable Trait Demo
     println!("\n2. 🎨 DRAWABLE TRAIT");
@@ -547,17 +1999,99 @@ fn main() {
     image.set_color("sepia");
     image.draw();
     
+    println!("Fading in the button:");
+    for progress in [0.0, 0.5, 1.0] {
+        if let Err(e) = button.draw_at_progress(&mut std::io::stdout(), progress) {
+            println!("Failed to draw: {}", e);
+        }
+    }
+    
+    let dark = Theme { fg: "white".to_string(), bg: "black".to_string(), accent: "orange".to_string() };
+    let mut widgets: Vec<Box<dyn Drawable>> = vec![Box::new(button), Box::new(image)];
+    for widget in &mut widgets {
+        widget.apply_theme(&dark);
+        widget.draw();
+    }
+    
     // 3. Serializable Trait Demo
     println!("\n3. 📄 SERIALIZABLE TRAIT");
     println!("{}", "-".repeat(20));
     let user = User { name: "John Doe".to_string(), age: 30 };
     println!("User JSON: {}", user.to_json());
     println!("User bytes length: {}", user.to_bytes().len());
+    println!("User XML: {}", user.to_xml());
+    let cartoon = User { name: "Tom & <Jerry>".to_string(), age: 80 };
+    println!("Escaped user XML: {}", cartoon.to_xml());
     
     match User::from_json(r#"{"name": "Jane", "age": 25}"#) {
         Ok(parsed_user) => println!("Parsed user: {:?}", parsed_user),
         Err(e) => println!("Parse error: {}", e),
     }
+    match User::from_json(&user.to_json()) {
+        Ok(parsed_user) => println!("Round trip matches: {}", parsed_user == user),
+        Err(e) => println!("Parse error: {}", e),
+    }
+    for malformed in [r#"{"name": "Jane"}"#, r#"{"name": "Jane", "age": "old"}"#, r#"{"name": "Jane", "age": 25"#] {
+        if let Err(e) = User::from_json(malformed) {
+            println!("Rejected {}: {}", malformed, e);
+        }
+    }
+    
+    let nested = r#"{"team": "core", "tags": ["a", "b\"c", "\u00e9\ud83d\ude00"], "lead": {"name": "Ann", "age": 41, "admin": true}, "deputy": null}"#;
+    match json::parse(nested) {
+        Ok(value) => {
+            let compact = json::to_json_string(&value);
+            println!("Parsed value written back: {}", compact);
+            println!("Pretty:\n{}", json::to_json_pretty(&value));
+            println!("Round trip matches: {}", json::parse(&compact).is_ok_and(|reparsed| reparsed == value));
+        }
+        Err(e) => println!("Parse error: {}", e),
+    }
+    let awkward = json::Value::String("quote \" backslash \\ newline \n bell \u{7}".to_string());
+    println!("Escaped: {}", json::to_json_string(&awkward));
+    for malformed in [r#"{"a": [1, 2,]}"#, r#"{"a": "unterminated}"#, "[1, 2] 3"] {
+        if let Err(e) = json::parse(malformed) {
+            println!("Rejected {}: {}", malformed, e);
+        }
+    }
+    
+    let team = Team {
+        lead: user.clone(),
+        deputy: None,
+        members: vec![
+            User { name: "Alice".to_string(), age: 28 },
+            User { name: "Bob".to_string(), age: 35 },
+        ],
+    };
+    let users_json = team.members.to_json();
+    println!("Users JSON: {}", users_json);
+    match Vec::<User>::from_json(&users_json) {
+        Ok(parsed_users) => println!("Parsed {} users: {:?}", parsed_users.len(), parsed_users),
+        Err(e) => println!("Parse error: {}", e),
+    }
+    
+    let many_users: Vec<User> = (0..1000).map(|i| User { name: format!("User {}", i), age: 20 + i % 50 }).collect();
+    let mut streamed = Vec::new();
+    match many_users.serialize_to(&mut streamed) {
+        Ok(()) => println!("Streamed {} bytes for 1000 users, identical to to_json: {}",
+                           streamed.len(), streamed == many_users.to_json().into_bytes()),
+        Err(e) => println!("Streaming failed: {}", e),
+    }
+    
+    let deputy = Some(team.members[0].clone());
+    println!("Some deputy JSON: {}, no deputy JSON: {}", deputy.to_json(), team.deputy.to_json());
+    match Option::<User>::from_json("null") {
+        Ok(parsed_deputy) => println!("Parsed null deputy: {:?}", parsed_deputy),
+        Err(e) => println!("Parse error: {}", e),
+    }
+    let team = Team { deputy, ..team };
+    let team_json = team.to_json();
+    println!("Team JSON: {}", team_json);
+    println!("Team XML: {}", team.to_xml());
+    match Team::from_json(&team_json) {
+        Ok(parsed_team) => println!("Parsed team: {:?}", parsed_team),
+        Err(e) => println!("Parse error: {}", e),
+    }
     
     // 4. Validator Trait Demo
     println!("\n4. ✅ VALIDATOR TRAIT");
@@ -573,6 +2107,33 @@ fn main() {
     if let Err(e) = invalid_email.validate() {
         println!("Validation error: {:?}", e);
     }
+    println!("All problems with the empty email: {:?}", empty_email.validate_all());
+    
+    for candidate in ["user@example.com", "no-at-symbol", ""] {
+        let rules = NonEmpty(candidate).and(ContainsAt(candidate));
+        println!("Combined rules for {:?}: {:?}", candidate, rules.validate());
+    }
+    
+    // Usernames of 3 to 8 characters, ages of 13 to 120
+    for username in ["al", "ali", "alexandr", "alexandra"] {
+        if let Ok(rule) = LengthValidator::new(username, 3, 8) {
+            match rule.validate() {
+                Ok(()) => println!("Username {:?}: ok", username),
+                Err(e) => println!("Username {:?}: {}", username, e),
+            }
+        }
+    }
+    for age in [12, 13, 120, 121] {
+        if let Ok(rule) = RangeValidator::new(age, 13, 120) {
+            match rule.validate() {
+                Ok(()) => println!("Age {}: ok", age),
+                Err(e) => println!("Age {}: {}", age, e),
+            }
+        }
+    }
+    if let Err(e) = RangeValidator::new(5, 10, 1) {
+        println!("Range error: {}", e);
+    }
     
     // 5. Cache Trait Demo
     println!("\n5. 💾 CACHE TRAIT");
@@ -587,13 +2148,53 @@ fn main() {
     println!("Get user:3: {:?}", cache.get(&"user:3".to_string()));
     
     cache.remove(&"user:1".to_string());
-    println!("After removal, user:1: {:?}", cache.get(&"user:1".to_string()));
+    println!("After removal, user:1: {:?} ({} entry left)", cache.get(&"user:1".to_string()), cache.len());
+    
+    cache.clear();
+    println!("After clear, user:2: {:?}", cache.get(&"user:2".to_string()));
+    
+    let stats = cache.stats();
+    println!("Cache stats: {} hits, {} misses, hit ratio {:.3}", stats.hits, stats.misses, stats.hit_ratio());
+    cache.reset_stats();
+    println!("Stats after reset: {:?}", cache.stats());
+    
+    let mut lru = LruCache::with_capacity(3);
+    lru.put("a", 1);
+    lru.put("b", 2);
+    lru.put("c", 3);
+    // Reading "a" makes "b" the least recently used, so it's the one evicted for "d"
+    lru.get(&"a");
+    lru.put("d", 4);
+    println!("LRU after adding d: a={:?} b={:?} c={:?} d={:?}", lru.get(&"a"), lru.get(&"b"), lru.get(&"c"), lru.get(&"d"));
+    
+    let mut sessions = TtlCache::new(Duration::from_millis(30));
+    sessions.put("session:1", "alice");
+    sessions.put("session:2", "bob");
+    println!("Session 1 before expiry: {:?}", sessions.get(&"session:1"));
+    thread::sleep(Duration::from_millis(40));
+    sessions.put("session:3", "carol");
+    println!("Session 1 after expiry: {:?}, session 3: {:?}", sessions.get(&"session:1"), sessions.get(&"session:3"));
+    println!("Removing expired session 2: {:?}", sessions.remove(&"session:2"));
+    println!("Purged {} expired session(s)", sessions.purge_expired());
+    
+    let mut fetches = 0;
+    let mut profiles = CachedRetry::new(|id: &u32| {
+        fetches += 1;
+        println!("Fetching profile {} (fetch #{})", id, fetches);
+        if fetches == 1 { Err("timeout".to_string()) } else { Ok(format!("Profile of user {}", id)) }
+    }, 3, Duration::from_millis(5));
+    for _ in 0..2 {
+        match profiles.get(&7) {
+            Ok(profile) => println!("Got: {}", profile),
+            Err(e) => println!("Lookup failed: {}", e),
+        }
+    }
     
     // 6. Logger Trait Demo
     println!("\n6. 📝 LOGGER TRAIT");
     println!("{}", "-".repeat(20));
     let console_logger = ConsoleLogger;
-    let file_logger = FileLogger { path: "app.log".to_string() };
+    let file_logger = FileLogger::new("app.log", 1024 * 1024, 3);
     
     console_logger.info("Application started");
     console_logger.warn("Low memory warning");
@@ -602,6 +2203,35 @@ fn main() {
     file_logger.info("File log entry");
     file_logger.error("Critical error logged to file");
     
+    // A tiny size limit forces rotation; the older lines end up in the `.1` file
+    let rotation_path = std::env::temp_dir().join("trait-examples-rotation.log");
+    let rotation_path = rotation_path.to_string_lossy().to_string();
+    let rotating_logger = FileLogger::new(&rotation_path, 120, 2);
+    for i in 1..=4 {
+        rotating_logger.info(&format!("Rotating entry {}", i));
+    }
+    let rotated = fs::read_to_string(rotating_logger.rotated_path(1)).unwrap_or_default();
+    println!("Rotated file holds {} older line(s), current file holds {}",
+             rotated.lines().count(),
+             fs::read_to_string(&rotation_path).unwrap_or_default().lines().count());
+    for path in [rotation_path.clone(), rotating_logger.rotated_path(1), rotating_logger.rotated_path(2)] {
+        let _ = fs::remove_file(path);
+    }
+    
+    println!("Fixed clock: {}", format_timestamp(1_704_164_645));
+    
+    // Info is below the Warn threshold and gets dropped
+    let quiet_logger = LevelLogger::new(ConsoleLogger, LogLevel::Warn);
+    quiet_logger.info("Cache warmed up");
+    quiet_logger.warn("Cache nearly full");
+    quiet_logger.error("Cache eviction failed");
+    println!("Warn-threshold logger: info enabled = {}, error enabled = {}",
+             quiet_logger.enabled(&LogLevel::Info), quiet_logger.enabled(&LogLevel::Error));
+    
+    // Only the failing result gets logged
+    let _ = NumberProcessor.process(5).log_err(&console_logger);
+    let _ = NumberProcessor.process(-5).log_err(&console_logger);
+    
     // 7. Comparable Trait Demo
     println!("\n7. ⚖️ COMPARABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -613,6 +2243,35 @@ fn main() {
     println!("{} > {}: {}", student2.name, student3.name, student2.is_greater_than(&student3));
     println!("{} < {}: {}", student3.name, student1.name, student3.is_less_than(&student1));
     
+    let small = Circle { radius: 1.0 };
+    let large = Circle { radius: 2.5 };
+    println!("Circle r=2.5 > circle r=1: {}", large.is_greater_than(&small));
+    let mut rectangles = [
+        Rectangle { width: 4.0, height: 5.0 },
+        Rectangle { width: 2.0, height: 3.0 },
+        Rectangle { width: 10.0, height: 1.0 },
+    ];
+    rectangles.sort_by(|a, b| a.compare(b));
+    let areas: Vec<f64> = rectangles.iter().map(Shape::area).collect();
+    println!("Rectangles sorted by area: {:?}", areas);
+    
+    let mut leaderboard = Leaderboard::new(3);
+    let students = [
+        student1,
+        student2,
+        student3,
+        Student { name: "Diana".to_string(), grade: 88.0 },
+        Student { name: "Eve".to_string(), grade: 70.5 },
+    ];
+    for student in &students {
+        if !leaderboard.submit(student.clone()) {
+            println!("{} ({}) didn't make the leaderboard", student.name, student.grade);
+        }
+    }
+    for (rank, student) in leaderboard.entries().iter().enumerate() {
+        println!("#{} {} ({})", rank + 1, student.name, student.grade);
+    }
+    
     // 8. Configurable Trait Demo
     println!("\n8. ⚙️ CONFIGURABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -624,10 +2283,37 @@ fn main() {
     println!("App name: {}", app.get_config_or_default("app_name", "Unknown"));
     println!("Port: {}", app.get_config_or_default("port", "3000"));
     
-    if let Ok(_) = app.load_from_file("config.json") {
-        println!("Config loaded successfully");
-        println!("Debug mode: {}", app.get_config_or_default("debug", "false"));
-        println!("Port after load: {}", app.get_config_or_default("port", "3000"));
+    let config_path = std::env::temp_dir().join("trait_examples_app.conf");
+    let config_path = config_path.to_string_lossy();
+    let written = fs::write(&*config_path, "# Local overrides\ndebug = true\n\nport = 8080\n");
+    if let Err(e) = written {
+        println!("Could not write sample config: {}", e);
+    }
+    match app.load_from_file(&config_path) {
+        Ok(()) => {
+            println!("Config loaded successfully");
+            println!("Debug mode: {}", app.get_config_or_default("debug", "false"));
+            println!("Port after load: {}", app.get_config_or_default("port", "3000"));
+        }
+        Err(e) => println!("Config error: {}", e),
+    }
+    // Environment variables take priority over the file
+    std::env::set_var("APP_PORT", "9090");
+    app.apply_env_overrides("APP");
+    std::env::remove_var("APP_PORT");
+    println!("Port after APP_PORT=9090: {}", app.get_config_or_default("port", "3000"));
+    
+    let _ = fs::write(&*config_path, "debug = false\nport\n");
+    match app.load_from_file(&config_path) {
+        Ok(()) => println!("Malformed config loaded unexpectedly"),
+        Err(e) => println!("Config error: {} (debug still {})", e, app.get_config_or_default("debug", "false")),
+    }
+    let _ = fs::remove_file(&*config_path);
+    
+    match app.load_from_file("config.json") {
+        Ok(()) => println!("Loaded config.json"),
+        Err(ConfigError::NotFound(path)) => println!("No config file at {}, using defaults", path),
+        Err(e) => println!("Config error: {}", e),
     }
     
     // 9. Convertible Trait Demo
@@ -646,6 +2332,11 @@ fn main() {
         Err(_) => println!("Conversion failed"),
     }
     
+    let readings = [Celsius(-40.0), Celsius(0.0), Celsius(-300.0), Celsius(100.0)];
+    let (converted, errors) = convert_all(&readings);
+    let converted: Vec<f64> = converted.iter().map(|f| f.0).collect();
+    println!("Converted {:?}°F, {} reading(s) below absolute zero: {:?}", converted, errors.len(), errors);
+    
     // 10. Processable Trait Demo
     println!("\n10. ⚡ PROCESSABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -671,6 +2362,38 @@ fn main() {
         }
     }
     
+    println!("Dynamic processing results:");
+    let processors: Vec<Box<dyn DynProcessor>> = vec![
+        Box::new(DynAdapter::<_, String>::new(TextProcessor)),
+        Box::new(DynAdapter::<_, i32>::new(NumberProcessor)),
+    ];
+    let inputs: Vec<Box<dyn Any>> = vec![Box::new("dynamic".to_string()), Box::new(21)];
+    for (processor, input) in processors.iter().zip(inputs) {
+        match processor.process_boxed(input) {
+            Ok(output) => match (output.downcast_ref::<String>(), output.downcast_ref::<i32>()) {
+                (Some(text), _) => println!("  ✓ {}", text),
+                (_, Some(number)) => println!("  ✓ {}", number),
+                _ => println!("  ✓ (unknown output type)"),
+            },
+            Err(e) => println!("  ✗ Error: {}", e),
+        }
+    }
+    // Handing a processor the wrong input type is an error rather than a panic
+    if let Err(e) = processors[1].process_boxed(Box::new("not a number")) {
+        println!("  ✗ Error: {}", e);
+    }
+    
+    println!("Pipeline results:");
+    let pipeline = DynPipeline::new()
+        .then(Box::new(DynAdapter::<_, i32>::new(NumberProcessor)))
+        .then(Box::new(DynAdapter::<_, i32>::new(NumberProcessor)));
+    for input in [5, -5] {
+        match pipeline.run(Box::new(input)) {
+            Ok(output) => println!("  ✓ {} -> {:?}", input, output.downcast_ref::<i32>()),
+            Err(e) => println!("  ✗ {} -> Error: {}", input, e),
+        }
+    }
+    
     // 11. Queryable Trait Demo
     println!("\n11. 🔍 QUERYABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -691,6 +2414,10 @@ fn main() {
         println!("User at index 1: {} (age: {})", user.name, user.age);
     }
     
+    let youngest_first: Vec<&str> = user_repo.find_sorted_by(|u| u.age).iter().map(|u| u.name.as_str()).collect();
+    let oldest_first: Vec<&str> = user_repo.find_sorted_by_desc(|u| u.age).iter().map(|u| u.name.as_str()).collect();
+    println!("Youngest first: {:?}, oldest first: {:?}", youngest_first, oldest_first);
+    
     // 12. Encryptable Trait Demo
     println!("\n12. 🔐 ENCRYPTABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -711,17 +2438,53 @@ fn main() {
         Err(_) => println!("Encryption failed"),
     }
     
+    // Every byte of the key is used, so a key sharing only the first letter doesn't decrypt it
+    if let Ok(encrypted) = message.encrypt(&key) {
+        if let Ok(wrong) = Message::decrypt(&encrypted, &SimpleKey("myhat".to_string())) {
+            println!("Decrypted with the wrong key matches: {}", wrong.0 == message.0);
+        }
+    }
+    if let Err(e) = message.encrypt(&SimpleKey(String::new())) {
+        println!("Empty key rejected: {:?}", e);
+    }
+    
     // 13. Observable Trait Demo
     println!("\n13. 👁️ OBSERVABLE TRAIT");
     println!("{}", "-".repeat(20));
-    let user_events = EventEmitter::<&'static str>::new("UserEvents");
-    let system_events = EventEmitter::<i32>::new("SystemEvents");
+    let mut user_events = EventEmitter::<&'static str>::new("UserEvents");
+    let mut system_events = EventEmitter::<i32>::new("SystemEvents");
+    
+    // Shared counters show which observers actually ran
+    let audit_count = Rc::new(Cell::new(0));
+    let email_count = Rc::new(Cell::new(0));
+    let audit = {
+        let audit_count = Rc::clone(&audit_count);
+        user_events.subscribe(Box::new(move |event| {
+            audit_count.set(audit_count.get() + 1);
+            println!("  audit log: {}", event);
+        }))
+    };
+    {
+        let email_count = Rc::clone(&email_count);
+        user_events.subscribe(Box::new(move |_| email_count.set(email_count.get() + 1)));
+    }
+    system_events.subscribe(Box::new(|code| println!("  system code {}", code)));
     
     user_events.notify(&"User logged in");
+    println!("Unsubscribed audit: {}, again: {}", user_events.unsubscribe(audit), user_events.unsubscribe(audit));
     user_events.notify(&"User updated profile");
+    println!("Audit ran {} time(s), email ran {} time(s)", audit_count.get(), email_count.get());
     system_events.notify(&42);
     // system_events.notify(&"System maintenance scheduled"); // This would be a type error
     
+    let achievements = RefCell::new(AchievementTracker::new()
+        .with_achievement("Coin Collector", GameEvent::CoinCollected, 10)
+        .with_achievement("First Blood", GameEvent::EnemyDefeated, 1));
+    for _ in 0..12 {
+        achievements.notify(&GameEvent::CoinCollected);
+    }
+    println!("Unlocked after 12 coins: {:?}", achievements.borrow().unlocked());
+    
     // 14. Buildable Trait Demo
     println!("\n14. 🏗️ BUILDABLE TRAIT");
     println!("{}", "-".repeat(20));
@@ -736,31 +2499,981 @@ fn main() {
         Err(e) => println!("Build failed: {}", e),
     }
     
-    // Try building without required fields
-    let incomplete_car = CarBuilder::new()
-        .make("Honda")
-        .build();
+    // Try building without required fields (reset clears everything set so far)
+    let mut incomplete_builder = CarBuilder::new().make("Honda").model("Civic");
+    incomplete_builder.reset();
+    let incomplete_car = incomplete_builder.make("Honda").build();
     
     match incomplete_car {
         Ok(_) => println!("Unexpected success"),
         Err(e) => println!("Expected build failure: {}", e),
     }
     
+    let invalid_builders = [
+        CarBuilder::new().make("Benz").model("Velo").year(1885),
+        CarBuilder::new().make("Tesla").model("Model Z").year(current_year() + 2),
+        CarBuilder::new().make("").model("Mystery").year(2020),
+    ];
+    for builder in invalid_builders {
+        if let Err(e) = builder.build() {
+            println!("Expected build failure: {}", e);
+        }
+    }
+    
     // 15. Schedulable Trait Demo
     println!("\n15. ⏰ SCHEDULABLE TRAIT");
     println!("{}", "-".repeat(20));
-    let task1 = Task { id: 1, name: "Backup Database".to_string(), scheduled: false };
-    let task2 = Task { id: 2, name: "Send Emails".to_string(), scheduled: true };
+    // Tasks run their jobs on a Scheduler, which has its own thread
+    let scheduler = Rc::new(Scheduler::new());
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    let record = |name: &'static str| {
+        let fired = Arc::clone(&fired);
+        move || fired.lock().unwrap().push(name)
+    };
+    let task1 = Task::new(1, "Backup Database", &scheduler, record("Backup Database"));
+    let task2 = Task::new(2, "Send Emails", &scheduler, record("Send Emails"));
     
-    task1.schedule(Duration::from_secs(300)); // 5 minutes
-    task2.schedule_at(SystemTime::now());
+    task1.schedule(Duration::from_millis(50));
+    task2.schedule_at(SystemTime::now() + Duration::from_millis(50));
     task1.schedule_repeating(Duration::from_secs(3600)); // 1 hour
     
     println!("Task 1 scheduled: {}", task1.is_scheduled());
     println!("Task 2 scheduled: {}", task2.is_scheduled());
     
     task2.cancel();
+    println!("Task 2 scheduled after cancel: {}", task2.is_scheduled());
+    thread::sleep(Duration::from_millis(100));
+    println!("Task 1 scheduled after firing: {}", task1.is_scheduled());
+    println!("Jobs that ran: {:?}", fired.lock().unwrap());
+    
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut runner = TaskRunner::new();
+    runner.spawn(CountdownTask { name: "short".to_string(), remaining: 1, log: Rc::clone(&log) });
+    runner.spawn(CountdownTask { name: "long".to_string(), remaining: 3, log: Rc::clone(&log) });
+    let rounds = runner.run();
+    println!("Cooperative tasks finished in {} rounds: {}", rounds, log.borrow().join(", "));
+    
+    let mut quick = RepeatingTask::new("Heartbeat", Duration::from_millis(100), || {});
+    let mut sleepy = RepeatingTask::new("Slow Report", Duration::from_millis(20), || {
+        thread::sleep(Duration::from_millis(100));
+    });
+    let mut metrics = Metrics::new();
+    for cycle in 1..=3 {
+        let quick_status = metrics.time("heartbeat cycle", || quick.run_cycle());
+        let sleepy_status = metrics.time("slow report cycle", || sleepy.run_cycle());
+        println!("Cycle {}: {} -> {:?}, {} -> {:?}",
+                 cycle, quick.name, quick_status, sleepy.name, sleepy_status);
+    }
+    
+    let mut rng = Lcg::new(42);
+    let delays: Vec<Duration> = (0..4).map(|attempt| backoff_delay(attempt, Duration::from_millis(10), &mut rng)).collect();
+    println!("Backoff delays: {:?}", delays);
+    
+    let mut calls = 0;
+    let flaky = || {
+        calls += 1;
+        if calls < 3 { Err(format!("connection refused (call {})", calls)) } else { Ok(calls) }
+    };
+    match metrics.time("flaky retry", || retry_with_backoff(flaky, 5, Duration::from_millis(5), &mut rng)) {
+        Ok(calls) => println!("Flaky task succeeded on call {}", calls),
+        Err(e) => println!("Flaky task failed: {}", e),
+    }
+    
+    println!("Timings:");
+    for (name, count, total) in metrics.report() {
+        println!("  {}: {} run(s), {:?} in total", name, count, total);
+    }
     
     println!("\n🎉 All trait examples completed successfully!");
     println!("{}", "=".repeat(50));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Keeps every entry in memory so tests can check what was logged
+    #[derive(Default)]
+    struct MemoryLogger {
+        entries: RefCell<Vec<String>>,
+    }
+    
+    impl Logger for MemoryLogger {
+        fn write_entry(&self, level: &LogLevel, message: &str) {
+            self.entries.borrow_mut().push(format!("{:?}: {}", level, message));
+        }
+    }
+    
+    #[test]
+    fn missing_config_file_is_not_found() {
+        let mut app = Application::new();
+        match app.load_from_file("definitely/not/a/config.conf") {
+            Err(ConfigError::NotFound(path)) => assert_eq!(path, "definitely/not/a/config.conf"),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn log_err_logs_errors_and_passes_results_through() {
+        let logger = MemoryLogger::default();
+        let failed: Result<u32, String> = Err("disk full".to_string());
+        assert_eq!(failed.log_err(&logger), Err("disk full".to_string()));
+        assert_eq!(Ok::<u32, String>(7).log_err(&logger), Ok(7));
+        
+        assert_eq!(*logger.entries.borrow(), ["Error: \"disk full\""]);
+    }
+    
+    #[test]
+    fn circle_is_more_compact_than_a_rectangle_of_equal_area() {
+        let rectangle = Rectangle { width: 4.0, height: 1.0 };
+        let circle = Circle { radius: (rectangle.area() / std::f64::consts::PI).sqrt() };
+        assert!((circle.area() - rectangle.area()).abs() < 1e-9);
+        
+        assert!((circle.compactness() - 4.0 * std::f64::consts::PI).abs() < 1e-9);
+        assert!((rectangle.compactness() - 25.0).abs() < 1e-9);
+        assert!(circle.compactness() < rectangle.compactness());
+    }
+    
+    #[test]
+    fn unit_sphere_and_cuboid_volumes() {
+        let sphere = Sphere { radius: 1.0 };
+        assert!((sphere.volume() - 4.0 / 3.0 * std::f64::consts::PI).abs() < 1e-12);
+        assert!((sphere.surface_area() - 4.0 * std::f64::consts::PI).abs() < 1e-12);
+        
+        let cuboid = Cuboid { width: 2.0, height: 3.0, depth: 4.0 };
+        assert_eq!(cuboid.volume(), 24.0);
+        assert_eq!(cuboid.surface_area(), 52.0);
+    }
+    
+    #[test]
+    fn factory_creates_registered_shapes_and_checks_arity() {
+        let factory = ShapeFactory::with_default_shapes();
+        let circle = factory.create("circle", &[5.0]).unwrap();
+        assert_eq!(circle.name(), "Circle");
+        assert!((circle.area() - 25.0 * std::f64::consts::PI).abs() < 1e-9);
+        
+        assert_eq!(factory.create("circle", &[5.0, 1.0]).err(),
+                   Some("circle expects 1 parameter (radius), got 2".to_string()));
+        assert_eq!(factory.create("hexagon", &[1.0]).err(), Some("Unknown shape: hexagon".to_string()));
+    }
+    
+    fn user(name: &str, age: u32) -> User {
+        User { name: name.to_string(), age }
+    }
+    
+    #[test]
+    fn team_with_two_members_round_trips() {
+        let team = Team {
+            lead: user("Alice", 30),
+            deputy: None,
+            members: vec![user("Bob, \"the builder\"", 25), user("Carol {}", 41)],
+        };
+        let json = team.to_json();
+        assert_eq!(Team::from_json(&json), Ok(team.clone()));
+        
+        let with_deputy = Team { deputy: Some(user("Dave", 35)), ..team };
+        assert_eq!(Team::from_json(&with_deputy.to_json()), Ok(with_deputy));
+    }
+    
+    #[test]
+    fn vec_of_users_round_trips() {
+        let users = vec![user("Alice", 30), user("Bob, Jr.", 25), user("{Carol}", 41)];
+        let json = users.to_json();
+        assert_eq!(Vec::<User>::from_json(&json), Ok(users));
+        assert_eq!(Vec::<User>::from_json("[]"), Ok(Vec::new()));
+    }
+    
+    #[test]
+    fn option_is_null_or_the_inner_value() {
+        let some = Some(user("Alice", 30));
+        assert_eq!(some.to_json(), user("Alice", 30).to_json());
+        assert_eq!(Option::<User>::from_json(&some.to_json()), Ok(some));
+        
+        let none: Option<User> = None;
+        assert_eq!(none.to_json(), "null");
+        assert_eq!(Option::<User>::from_json(" null "), Ok(None));
+    }
+    
+    #[test]
+    fn leaderboard_keeps_the_top_three_grades_in_order() {
+        let mut board = Leaderboard::new(3);
+        let submitted: Vec<bool> = [("Ann", 72.0), ("Ben", 91.5), ("Dee", 88.0), ("Cal", 65.0), ("Eve", 95.0)]
+            .into_iter()
+            .map(|(name, grade)| board.submit(Student { name: name.to_string(), grade }))
+            .collect();
+        // Cal is below the full board's lowest grade, and Eve pushes Ann off the end
+        assert_eq!(submitted, [true, true, true, false, true]);
+        
+        let top: Vec<(&str, f64)> = board.entries().iter().map(|s| (s.name.as_str(), s.grade)).collect();
+        assert_eq!(top, [("Eve", 95.0), ("Ben", 91.5), ("Dee", 88.0)]);
+    }
+    
+    #[test]
+    fn dyn_processors_of_different_types_share_a_vec() {
+        let processors: Vec<Box<dyn DynProcessor>> = vec![
+            Box::new(DynAdapter::<_, String>::new(TextProcessor)),
+            Box::new(DynAdapter::<_, i32>::new(NumberProcessor)),
+        ];
+        let text = processors[0].process_boxed(Box::new("hello".to_string())).unwrap();
+        assert_eq!(text.downcast_ref::<String>().map(String::as_str), Some("HELLO"));
+        let number = processors[1].process_boxed(Box::new(21)).unwrap();
+        assert_eq!(number.downcast_ref::<i32>(), Some(&42));
+        
+        assert_eq!(processors[1].process_boxed(Box::new(-1)).err(), Some("Negative number".to_string()));
+        assert_eq!(processors[1].process_boxed(Box::new("21")).err(), Some("expected input of type i32".to_string()));
+    }
+    
+    // Counts how often it runs, passing its input straight through
+    struct CountingProcessor {
+        calls: Rc<Cell<u32>>,
+    }
+    
+    impl DynProcessor for CountingProcessor {
+        fn process_boxed(&self, input: Box<dyn Any>) -> Result<Box<dyn Any>, String> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(input)
+        }
+    }
+    
+    #[test]
+    fn pipeline_threads_the_value_through_each_stage() {
+        let pipeline = DynPipeline::new()
+            .then(Box::new(DynAdapter::<_, i32>::new(NumberProcessor)))
+            .then(Box::new(DynAdapter::<_, i32>::new(NumberProcessor)));
+        let output = pipeline.run(Box::new(5)).unwrap();
+        assert_eq!(output.downcast_ref::<i32>(), Some(&20));
+    }
+    
+    #[test]
+    fn pipeline_skips_later_stages_after_a_failure() {
+        let calls = Rc::new(Cell::new(0));
+        let pipeline = DynPipeline::new()
+            .then(Box::new(DynAdapter::<_, i32>::new(NumberProcessor)))
+            .then(Box::new(CountingProcessor { calls: Rc::clone(&calls) }));
+        
+        assert_eq!(pipeline.run(Box::new(-5)).err(), Some("stage 1 failed: Negative number".to_string()));
+        assert_eq!(calls.get(), 0);
+        
+        assert!(pipeline.run(Box::new(5)).is_ok());
+        assert_eq!(calls.get(), 1);
+    }
+    
+    #[test]
+    fn triangle_uses_herons_formula() {
+        let right = Triangle { a: 3.0, b: 4.0, c: 5.0 };
+        assert_eq!(right.area(), 6.0);
+        assert_eq!(right.perimeter(), 12.0);
+        assert_eq!(right.name(), "Triangle");
+        
+        // Too short to close, and flat
+        assert_eq!(Triangle { a: 1.0, b: 2.0, c: 10.0 }.area(), 0.0);
+        assert_eq!(Triangle { a: 1.0, b: 2.0, c: 3.0 }.area(), 0.0);
+    }
+    
+    #[test]
+    fn sleepy_task_times_out() {
+        let status = run_with_timeout(|| thread::sleep(Duration::from_millis(200)), Duration::from_millis(10));
+        assert_eq!(status, TaskStatus::TimedOut);
+        assert_eq!(run_with_timeout(|| {}, Duration::from_secs(1)), TaskStatus::Completed);
+    }
+    
+    #[test]
+    fn repeating_task_skips_the_cycle_after_a_timeout() {
+        let mut task = RepeatingTask::new("sleepy", Duration::from_millis(10), || thread::sleep(Duration::from_millis(50)));
+        assert_eq!(task.run_cycle(), TaskStatus::TimedOut);
+        assert_eq!(task.run_cycle(), TaskStatus::Skipped);
+        assert_eq!(task.run_cycle(), TaskStatus::TimedOut);
+    }
+    
+    #[test]
+    fn backoff_delays_grow_within_their_jitter_bounds() {
+        let base = Duration::from_millis(100);
+        let mut rng = Lcg::new(42);
+        let mut previous = Duration::ZERO;
+        for attempt in 0..8 {
+            let delay = backoff_delay(attempt, base, &mut rng);
+            let ceiling = base * 2u32.pow(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+            assert!(delay >= previous, "attempt {}: {:?} < {:?}", attempt, delay, previous);
+            previous = delay;
+        }
+    }
+    
+    #[test]
+    fn lcg_is_deterministic_and_in_range() {
+        let (mut a, mut b) = (Lcg::new(7), Lcg::new(7));
+        for _ in 0..1000 {
+            let x = a.next_f64();
+            assert_eq!(x, b.next_f64());
+            assert!((0.0..1.0).contains(&x));
+        }
+        assert_ne!(Lcg::new(1).next_u32(), Lcg::new(2).next_u32());
+    }
+    
+    #[test]
+    fn retry_gives_up_after_max_attempts() {
+        let mut rng = Lcg::new(1);
+        let mut calls = 0;
+        let result: Result<(), String> = retry_with_backoff(|| { calls += 1; Err("down".to_string()) }, 3, Duration::from_millis(1), &mut rng);
+        assert_eq!(result, Err("gave up after 3 attempts: down".to_string()));
+        assert_eq!(calls, 3);
+        
+        let mut calls = 0;
+        let result = retry_with_backoff(|| { calls += 1; if calls < 2 { Err("flaky".to_string()) } else { Ok(calls) } }, 5, Duration::from_millis(1), &mut rng);
+        assert_eq!(result, Ok(2));
+    }
+    
+    #[test]
+    fn retry_with_no_attempts_never_runs_the_task() {
+        let mut calls = 0;
+        let result: Result<(), String> = retry_with_backoff(|| { calls += 1; Ok(()) }, 0, Duration::from_millis(1), &mut Lcg::new(1));
+        assert_eq!(result, Err("no attempts allowed".to_string()));
+        assert_eq!(calls, 0);
+    }
+    
+    #[test]
+    fn bounding_boxes_of_concrete_and_default_shapes() {
+        assert_eq!(Circle { radius: 2.5 }.bounding_box(), (5.0, 5.0));
+        assert_eq!(Circle { radius: 0.0 }.bounding_box(), (0.0, 0.0));
+        assert_eq!(Rectangle { width: 4.0, height: 1.5 }.bounding_box(), (4.0, 1.5));
+        // Triangle falls back to the square with the same perimeter
+        assert_eq!(Triangle { a: 3.0, b: 4.0, c: 5.0 }.bounding_box(), (3.0, 3.0));
+    }
+    
+    // Only implements the required methods, so it gets the default draw_with_opacity
+    struct Label { drawn: Cell<u32> }
+    
+    impl Drawable for Label {
+        fn draw(&self) { self.drawn.set(self.drawn.get() + 1); }
+        fn set_color(&mut self, _color: &str) {}
+    }
+    
+    #[test]
+    fn half_progress_draws_at_half_opacity() {
+        let button = Button { text: "OK".to_string(), color: "blue".to_string() };
+        let mut out = Vec::new();
+        for progress in [-1.0, 0.5, 2.0] {
+            button.draw_at_progress(&mut out, progress).unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "Drawing button: OK (blue, 0% opacity)\nDrawing button: OK (blue, 50% opacity)\nDrawing button: OK (blue, 100% opacity)\n");
+    }
+    
+    #[test]
+    fn default_opacity_draws_anything_visible() {
+        let label = Label { drawn: Cell::new(0) };
+        let mut out = Vec::new();
+        label.draw_at_progress(&mut out, 0.0).unwrap();
+        assert_eq!(label.drawn.get(), 0);
+        label.draw_at_progress(&mut out, 0.5).unwrap();
+        assert_eq!(label.drawn.get(), 1);
+        assert!(out.is_empty());
+    }
+    
+    #[test]
+    fn scaling_by_three_makes_area_nine_times_bigger() {
+        let rectangle = Rectangle { width: 2.0, height: 5.0 };
+        let scaled = rectangle.scale(3.0);
+        assert_eq!((scaled.width, scaled.height), (6.0, 15.0));
+        assert_eq!(scaled.area(), 9.0 * rectangle.area());
+        
+        // Negative factors scale by their absolute value
+        let flipped = rectangle.scale(-3.0);
+        assert_eq!((flipped.width, flipped.height), (6.0, 15.0));
+        assert_eq!(Circle { radius: 1.5 }.scale(-2.0).radius, 3.0);
+    }
+    
+    #[test]
+    fn cached_retry_retries_once_then_serves_from_the_cache() {
+        let calls = Cell::new(0);
+        let mut profiles = CachedRetry::new(|id: &u32| {
+            calls.set(calls.get() + 1);
+            if calls.get() == 1 { Err("timeout".to_string()) } else { Ok(format!("Profile {}", id)) }
+        }, 3, Duration::from_millis(1));
+        
+        assert_eq!(profiles.get(&7), Ok(&"Profile 7".to_string()));
+        assert_eq!(calls.get(), 2);
+        assert_eq!(profiles.get(&7), Ok(&"Profile 7".to_string()));
+        assert_eq!(calls.get(), 2);
+    }
+    
+    #[test]
+    fn cached_retry_does_not_cache_failures() {
+        let calls = Cell::new(0);
+        let mut lookups = CachedRetry::new(|_: &u32| -> Result<u32, String> {
+            calls.set(calls.get() + 1);
+            Err("down".to_string())
+        }, 2, Duration::from_millis(1));
+        
+        assert_eq!(lookups.get(&1).err(), Some("gave up after 2 attempts: down".to_string()));
+        assert_eq!(lookups.get(&1).err(), Some("gave up after 2 attempts: down".to_string()));
+        assert_eq!(calls.get(), 4);
+    }
+    
+    #[test]
+    fn user_json_round_trips() {
+        let original = user("Zoë \"Z\" O'Neil\n", 42);
+        assert_eq!(User::from_json(&original.to_json()), Ok(original));
+    }
+    
+    #[test]
+    fn malformed_user_json_is_rejected() {
+        assert_eq!(User::from_json(r#"{"age": 3}"#), Err("missing field \"name\"".to_string()));
+        assert_eq!(User::from_json(r#"{"name": "Al", "age": "old"}"#),
+                   Err("\"age\" must be a non-negative integer, got String(\"old\")".to_string()));
+        assert_eq!(User::from_json(r#"{"name": "Al", "age": -1}"#),
+                   Err("\"age\" must be a non-negative integer, got Number(-1.0)".to_string()));
+        assert!(User::from_json(r#"{"name": "Al", "age": 3"#).is_err());
+    }
+    
+    #[test]
+    fn user_xml_escapes_special_characters() {
+        assert_eq!(user("John", 30).to_xml(), "<user><name>John</name><age>30</age></user>");
+        assert_eq!(user("Tom & <Jerry>", 8).to_xml(), "<user><name>Tom &amp; &lt;Jerry&gt;</name><age>8</age></user>");
+        // Types without their own to_xml get the escaped JSON in an envelope
+        let team = Team { lead: user("A&B", 1), deputy: None, members: Vec::new() };
+        assert_eq!(team.to_xml(), format!("<json>{}</json>", team.to_json().replace('&', "&amp;")));
+    }
+    
+    fn full_lru() -> LruCache<&'static str, u32> {
+        let mut cache = LruCache::with_capacity(3);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        cache.put("c", 3);
+        cache
+    }
+    
+    #[test]
+    fn lru_evicts_the_entry_not_touched() {
+        let mut cache = full_lru();
+        cache.get(&"b");
+        cache.get(&"c");
+        cache.put("d", 4);
+        assert!(!cache.contains_key(&"a"));
+        assert_eq!([cache.get(&"b"), cache.get(&"c"), cache.get(&"d")], [Some(&2), Some(&3), Some(&4)]);
+    }
+    
+    #[test]
+    fn lru_get_saves_the_oldest_key() {
+        let mut cache = full_lru();
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.put("d", 4);
+        assert!(cache.contains_key(&"a"));
+        assert!(!cache.contains_key(&"b"));
+        
+        // Replacing a value counts as a use and doesn't evict anything, so "d" is now the oldest
+        cache.put("c", 30);
+        cache.put("e", 5);
+        assert_eq!([cache.get(&"a"), cache.get(&"c"), cache.get(&"d")], [Some(&1), Some(&30), None]);
+    }
+    
+    #[test]
+    fn ttl_entries_expire_and_are_purged() {
+        let mut cache = TtlCache::new(Duration::from_millis(20));
+        cache.put("session", 1);
+        cache.put("other", 2);
+        assert_eq!(cache.get(&"session"), Some(&1));
+        
+        thread::sleep(Duration::from_millis(30));
+        cache.put("fresh", 3);
+        assert_eq!(cache.get(&"session"), None);
+        assert!(!cache.contains_key(&"other"));
+        // Expired but not purged yet
+        assert_eq!(cache.remove(&"session"), None);
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.get(&"fresh"), Some(&3));
+    }
+    
+    #[test]
+    fn zero_ttl_expires_immediately() {
+        let mut cache = TtlCache::new(Duration::ZERO);
+        cache.put(1, "gone");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.purge_expired(), 1);
+    }
+    
+    #[test]
+    fn two_hits_and_a_miss_is_two_thirds() {
+        let mut cache = MemoryCache::new();
+        cache.put("a", 1);
+        cache.get(&"a");
+        cache.get(&"a");
+        cache.get(&"b");
+        // Not a lookup
+        assert!(cache.contains_key(&"a"));
+        
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1 });
+        assert!((cache.stats().hit_ratio() - 2.0 / 3.0).abs() < 1e-12);
+        
+        cache.reset_stats();
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 0 });
+        assert_eq!(cache.stats().hit_ratio(), 0.0);
+    }
+    
+    #[test]
+    fn warn_threshold_drops_info_and_keeps_errors() {
+        assert!(LogLevel::Info < LogLevel::Warn && LogLevel::Warn < LogLevel::Error);
+        
+        let logger = LevelLogger::new(MemoryLogger::default(), LogLevel::Warn);
+        logger.log(LogLevel::Info, "noise");
+        logger.log(LogLevel::Warn, "disk 90% full");
+        logger.log(LogLevel::Error, "disk full");
+        assert_eq!(*logger.inner.entries.borrow(), ["Warn: disk 90% full", "Error: disk full"]);
+    }
+    
+    // Pins the clock so formatted lines are reproducible
+    struct FixedClockLogger;
+    
+    impl Logger for FixedClockLogger {
+        fn write_entry(&self, _level: &LogLevel, _message: &str) {}
+        fn timestamp(&self) -> String { format_timestamp(1_704_164_645) }
+    }
+    
+    #[test]
+    fn entries_start_with_a_utc_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(FixedClockLogger.format_entry(&LogLevel::Error, "message"), "[2024-01-02T03:04:05Z][Error] message");
+        
+        // The real clock gives the same shape: YYYY-MM-DDTHH:MM:SSZ
+        let now = now_string();
+        let shape: String = now.chars().map(|c| if c.is_ascii_digit() { '0' } else { c }).collect();
+        assert_eq!(shape, "0000-00-00T00:00:00Z");
+    }
+    
+    #[test]
+    fn file_logger_rotates_without_losing_the_triggering_line() {
+        let dir = std::env::temp_dir().join(format!("trait-examples-rotation-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        let logger = FileLogger::new(path.to_str().unwrap(), 120, 2);
+        
+        // Each line is about 50 bytes with the fixed-width timestamp, so the third one forces a rotation
+        for message in ["first line of the log", "second line of the l", "third line of the log"] {
+            logger.log(LogLevel::Info, message);
+        }
+        let current = fs::read_to_string(&path).unwrap();
+        let rotated = fs::read_to_string(dir.join("app.log.1")).unwrap();
+        assert_eq!(current.lines().count(), 1);
+        assert!(current.ends_with("[Info] third line of the log\n"), "{}", current);
+        assert_eq!(rotated.lines().count(), 2);
+        assert!(rotated.contains("first line") && rotated.contains("second line"), "{}", rotated);
+        assert!(fs::metadata(dir.join("app.log.2")).is_err());
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn broken_email_reports_every_problem() {
+        let errors = Email(String::new()).validate_all();
+        assert!(matches!(errors[..], [EmailError::Empty, EmailError::NoAtSymbol, EmailError::InvalidFormat]), "{:?}", errors);
+        assert!(matches!(Email("user@localhost".to_string()).validate_all()[..], [EmailError::InvalidFormat]));
+        assert!(Email("user@example.com".to_string()).validate_all().is_empty());
+    }
+    
+    #[test]
+    fn combined_validators_need_both_to_pass() {
+        let rules = |candidate| NonEmpty(candidate).and(ContainsAt(candidate));
+        assert!(rules("user@example.com").validate().is_ok());
+        assert!(matches!(rules("").validate(), Err(AndError::First(EmailError::Empty))));
+        assert!(matches!(rules("user").validate(), Err(AndError::Second(EmailError::NoAtSymbol))));
+    }
+    
+    #[test]
+    fn length_validator_boundaries() {
+        let check = |value| LengthValidator::new(value, 3, 5).unwrap().validate();
+        assert!(matches!(check("ab"), Err(LengthError::TooShort { min: 3, actual: 2 })));
+        assert!(check("abc").is_ok());
+        assert!(check("abcde").is_ok());
+        assert!(matches!(check("abcdef"), Err(LengthError::TooLong { max: 5, actual: 6 })));
+        // Characters, not bytes
+        assert!(check("ñññ").is_ok());
+        
+        let exact = |value| LengthValidator::new(value, 4, 4).unwrap().validate();
+        assert!(exact("abcd").is_ok());
+        assert!(exact("abc").is_err() && exact("abcde").is_err());
+        assert!(LengthValidator::new("", 5, 4).is_err());
+    }
+    
+    #[test]
+    fn range_validator_boundaries() {
+        let check = |age| RangeValidator::new(age, 18, 65).unwrap().validate();
+        assert!(matches!(check(17), Err(RangeError::BelowMin { min: 18, actual: 17 })));
+        assert!(check(18).is_ok());
+        assert!(check(65).is_ok());
+        assert!(matches!(check(66), Err(RangeError::AboveMax { max: 65, actual: 66 })));
+        
+        assert!(RangeValidator::new(1.0, 2.0, 1.0).is_err());
+        assert!(RangeValidator::new(1.0, f64::NAN, 2.0).is_err());
+    }
+    
+    #[test]
+    fn coin_achievement_unlocks_exactly_once() {
+        let achievements = RefCell::new(AchievementTracker::new()
+            .with_achievement("Coin Collector", GameEvent::CoinCollected, 10)
+            .with_achievement("First Blood", GameEvent::EnemyDefeated, 1));
+        for _ in 0..9 {
+            achievements.notify(&GameEvent::CoinCollected);
+        }
+        assert!(achievements.borrow().unlocked().is_empty());
+        for _ in 0..5 {
+            achievements.notify(&GameEvent::CoinCollected);
+        }
+        assert_eq!(achievements.borrow().unlocked(), ["Coin Collector"]);
+        
+        achievements.notify(&GameEvent::EnemyDefeated);
+        assert_eq!(achievements.borrow().unlocked(), ["Coin Collector", "First Blood"]);
+    }
+    
+    #[test]
+    fn users_sort_by_age_either_way() {
+        let repo = UserRepository::new();
+        let names = |users: Vec<&User>| users.iter().map(|u| u.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(repo.find_sorted_by(|u| u.age)), ["Bob", "Alice", "Charlie"]);
+        assert_eq!(names(repo.find_sorted_by_desc(|u| u.age)), ["Charlie", "Alice", "Bob"]);
+        assert_eq!(names(repo.find_sorted_by(|u| u.name.len())), ["Bob", "Alice", "Charlie"]);
+    }
+    
+    #[test]
+    fn serialize_to_streams_same_bytes_as_to_json() {
+        let users: Vec<User> = (0..1000).map(|i| user(&format!("User \"{}\"", i), 20 + i % 50)).collect();
+        let mut streamed = Vec::new();
+        users.serialize_to(&mut streamed).unwrap();
+        assert_eq!(streamed, users.to_json().into_bytes());
+        
+        let mut empty = Vec::new();
+        Vec::<User>::new().serialize_to(&mut empty).unwrap();
+        assert_eq!(empty, b"[]");
+    }
+    
+    // Load test for MemoryCache: an accidental O(n) lookup shows up as a blow-up in the read
+    // time. Slow, so run it with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn stress_memory_cache() {
+        const COUNT: u64 = 100_000;
+        let mut cache = MemoryCache::new();
+        
+        let start = Instant::now();
+        for key in 0..COUNT {
+            cache.put(key, key * 2);
+        }
+        let write_time = start.elapsed();
+        assert_eq!(cache.len(), COUNT as usize);
+        
+        let start = Instant::now();
+        for key in 0..COUNT {
+            assert_eq!(cache.get(&key), Some(&(key * 2)), "key {}", key);
+        }
+        let read_time = start.elapsed();
+        assert_eq!(cache.get(&COUNT), None);
+        
+        assert_eq!(cache.stats(), CacheStats { hits: COUNT, misses: 1 });
+        assert_eq!(cache.len(), COUNT as usize);
+        println!("{} entries: written in {:?}, read back in {:?}", COUNT, write_time, read_time);
+    }
+    
+    #[test]
+    fn xor_cipher_uses_the_whole_key() {
+        let key = SimpleKey("s3cr3t".to_string());
+        let message = Message("Attack at dawn!".to_string());
+        let encrypted = message.encrypt(&key).unwrap();
+        assert_ne!(encrypted, message.0.as_bytes());
+        assert_eq!(Message::decrypt(&encrypted, &key).unwrap().0, "Attack at dawn!");
+        
+        // Keys sharing a first byte used to encrypt identically
+        let other_key = SimpleKey("sXXXXX".to_string());
+        assert_ne!(message.encrypt(&other_key).unwrap(), encrypted);
+        assert_ne!(Message::decrypt(&encrypted, &other_key).unwrap().0, "Attack at dawn!");
+    }
+    
+    #[test]
+    fn xor_cipher_rejects_an_empty_key() {
+        let key = SimpleKey(String::new());
+        assert!(matches!(Message("hi".to_string()).encrypt(&key), Err(CryptoError::EmptyKey)));
+        assert!(matches!(Message::decrypt(b"hi", &key), Err(CryptoError::EmptyKey)));
+    }
+    
+    #[test]
+    fn shapes_compare_by_area() {
+        use std::cmp::Ordering;
+        
+        let small = Circle { radius: 1.0 };
+        let large = Circle { radius: 2.0 };
+        assert_eq!(large.compare(&small), Ordering::Greater);
+        assert_eq!(small.compare(&Circle { radius: 1.0 }), Ordering::Equal);
+        assert!(large.is_greater_than(&small));
+        assert!(!small.is_greater_than(&large));
+        assert!(small.is_less_than(&large));
+        
+        // 2x8 and 4x4 share an area, though neither side matches
+        let wide = Rectangle { width: 2.0, height: 8.0 };
+        let square = Rectangle { width: 4.0, height: 4.0 };
+        assert_eq!(wide.compare(&square), Ordering::Equal);
+        assert!(Rectangle { width: 5.0, height: 4.0 }.is_greater_than(&square));
+    }
+    
+    #[test]
+    fn metrics_count_each_named_operation() {
+        let mut metrics = Metrics::new();
+        assert_eq!(metrics.time("parse", || 1 + 1), 2);
+        metrics.time("parse", || thread::sleep(Duration::from_millis(2)));
+        metrics.time("render", || ());
+        
+        let report = metrics.report();
+        let summary: Vec<(&str, u64)> = report.iter().map(|(name, count, _)| (name.as_str(), *count)).collect();
+        assert_eq!(summary, [("parse", 2), ("render", 1)]);
+        assert!(report[0].2 >= Duration::from_millis(2));
+    }
+    
+    #[test]
+    fn event_emitter_notifies_current_subscribers() {
+        let first = Rc::new(Cell::new(0));
+        let second = Rc::new(Cell::new(0));
+        let mut emitter = EventEmitter::new("test");
+        let first_handle = emitter.subscribe(Box::new({
+            let first = Rc::clone(&first);
+            move |n: &u32| first.set(first.get() + n)
+        }));
+        emitter.subscribe(Box::new({
+            let second = Rc::clone(&second);
+            move |n: &u32| second.set(second.get() + n)
+        }));
+        
+        emitter.notify(&5);
+        assert_eq!((first.get(), second.get()), (5, 5));
+        
+        assert!(emitter.unsubscribe(first_handle));
+        emitter.notify(&1);
+        assert_eq!((first.get(), second.get()), (5, 6));
+        
+        // Handles are never reused, so a stale one stays invalid after later subscriptions
+        emitter.subscribe(Box::new(|_: &u32| {}));
+        assert!(!emitter.unsubscribe(first_handle));
+    }
+    
+    #[test]
+    fn scheduler_runs_due_jobs_and_skips_cancelled_ones() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let scheduler = Scheduler::new();
+        let record = |name: &'static str| {
+            let fired = Arc::clone(&fired);
+            move || fired.lock().unwrap().push(name)
+        };
+        let kept = scheduler.schedule(Duration::from_millis(50), record("kept"));
+        let cancelled = scheduler.schedule(Duration::from_millis(50), record("cancelled"));
+        
+        assert!(kept.is_scheduled());
+        assert!(cancelled.cancel());
+        assert!(!cancelled.is_scheduled());
+        assert!(!cancelled.cancel());
+        
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(*fired.lock().unwrap(), ["kept"]);
+        assert!(!kept.is_scheduled());
+        // Too late: it already fired
+        assert!(!kept.cancel());
+        
+        // Dropping joins the worker; a job that hasn't fired yet never runs
+        scheduler.schedule(Duration::from_secs(60), record("never"));
+        drop(scheduler);
+        assert_eq!(*fired.lock().unwrap(), ["kept"]);
+    }
+    
+    #[test]
+    fn tasks_schedule_through_the_scheduler() {
+        let runs = Arc::new(Mutex::new(0));
+        let scheduler = Rc::new(Scheduler::new());
+        let counter = |runs: &Arc<Mutex<u32>>| {
+            let runs = Arc::clone(runs);
+            move || *runs.lock().unwrap() += 1
+        };
+        let task = Task::new(1, "Backup", &scheduler, counter(&runs));
+        let cancelled = Task::new(2, "Emails", &scheduler, counter(&runs));
+        assert!(!task.is_scheduled());
+        
+        task.schedule(Duration::from_millis(50));
+        cancelled.schedule_at(SystemTime::now() + Duration::from_millis(50));
+        assert!(task.is_scheduled());
+        assert!(cancelled.is_scheduled());
+        cancelled.cancel();
+        assert!(!cancelled.is_scheduled());
+        
+        thread::sleep(Duration::from_millis(150));
+        assert!(!task.is_scheduled());
+        assert_eq!(*runs.lock().unwrap(), 1);
+        
+        // Rescheduling replaces the pending run rather than adding a second one
+        task.schedule(Duration::from_millis(20));
+        task.schedule(Duration::from_millis(20));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(*runs.lock().unwrap(), 2);
+    }
+    
+    #[test]
+    fn apply_theme_recolors_every_widget() {
+        struct Label { color: String }
+        impl Drawable for Label {
+            fn draw(&self) {}
+            fn set_color(&mut self, color: &str) { self.color = color.to_string(); }
+        }
+        
+        let theme = Theme { fg: "white".to_string(), bg: "black".to_string(), accent: "orange".to_string() };
+        let mut button = Button { text: "OK".to_string(), color: "grey".to_string() };
+        let mut image = Image { path: "logo.png".to_string(), color: "grey".to_string() };
+        let mut label = Label { color: "grey".to_string() };
+        for widget in [&mut button as &mut dyn Drawable, &mut image, &mut label] {
+            widget.apply_theme(&theme);
+        }
+        
+        assert_eq!(button.color, "orange");
+        assert_eq!(image.color, "black");
+        // Widgets without an override take the foreground color
+        assert_eq!(label.color, "white");
+    }
+    
+    #[test]
+    fn convert_all_splits_successes_from_errors() {
+        let fahrenheit = |converted: Vec<Fahrenheit>| -> Vec<f64> { converted.iter().map(|f| f.0).collect() };
+        
+        let (converted, errors) = convert_all(&[Celsius(0.0), Celsius(100.0), Celsius(-40.0)]);
+        assert_eq!(fahrenheit(converted), [32.0, 212.0, -40.0]);
+        assert!(errors.is_empty());
+        
+        let (converted, errors) = convert_all(&[Celsius(0.0), Celsius(-300.0), Celsius(ABSOLUTE_ZERO_CELSIUS)]);
+        assert_eq!(converted.len(), 2);
+        assert_eq!(converted[0].0, 32.0);
+        assert!((converted[1].0 - -459.67).abs() < 1e-9);
+        assert_eq!(errors.len(), 1);
+    }
+    
+    #[test]
+    fn convert_from_rejects_below_absolute_zero() {
+        assert_eq!(Celsius::convert_from(Fahrenheit(212.0)).unwrap().0, 100.0);
+        assert!(Celsius::convert_from(Fahrenheit(-500.0)).is_err());
+    }
+    
+    fn build_error(builder: CarBuilder) -> Option<String> {
+        builder.build().err()
+    }
+    
+    #[test]
+    fn car_builder_validates_fields() {
+        let valid = CarBuilder::new().make("Toyota").model("Camry").year(2023);
+        let Ok(car) = valid.clone().build() else { panic!("valid car rejected") };
+        assert_eq!((car.make.as_str(), car.model.as_str(), car.year), ("Toyota", "Camry", 2023));
+        
+        let latest = current_year() + 1;
+        assert!(valid.clone().year(latest).build().is_ok());
+        assert_eq!(build_error(valid.clone().year(1885)), Some(format!("Year 1885 is outside 1886..={}", latest)));
+        assert_eq!(build_error(valid.clone().year(latest + 1)),
+                   Some(format!("Year {} is outside 1886..={}", latest + 1, latest)));
+        assert_eq!(build_error(valid.clone().make("")), Some("Make must not be empty".to_string()));
+        assert_eq!(build_error(valid.model("  ")), Some("Model must not be empty".to_string()));
+        assert_eq!(build_error(CarBuilder::new().model("Camry").year(2023)), Some("Make is required".to_string()));
+    }
+    
+    #[test]
+    fn shape_scene_queries_only_overlapping_shapes() {
+        let mut scene = ShapeScene::new(10.0);
+        scene.add(0.0, 0.0, Box::new(Circle { radius: 2.0 }));
+        scene.add(25.0, 5.0, Box::new(Rectangle { width: 4.0, height: 3.0 }));
+        scene.add(50.0, 50.0, Box::new(Triangle { a: 3.0, b: 4.0, c: 5.0 }));
+        // Spans the corner of four cells
+        scene.add(9.0, 9.0, Box::new(Circle { radius: 1.0 }));
+        let names = |x, y, w, h| -> Vec<String> {
+            scene.query_region(x, y, w, h).iter().map(|shape| shape.name().to_string()).collect()
+        };
+        
+        assert_eq!(names(0.0, 0.0, 30.0, 10.0), ["Circle", "Rectangle", "Circle"]);
+        assert_eq!(names(45.0, 45.0, 10.0, 10.0), ["Triangle"]);
+        assert!(names(10.0, 20.0, 5.0, 5.0).is_empty());
+        // Listed in several cells but returned once
+        assert_eq!(names(10.5, 10.5, 1.0, 1.0), ["Circle"]);
+        // Shares a cell with the small circle without overlapping it
+        assert!(names(12.0, 12.0, 5.0, 5.0).is_empty());
+        // Touching edges count
+        assert_eq!(names(-5.0, -5.0, 5.0, 5.0), ["Circle"]);
+    }
+    
+    #[test]
+    fn load_from_file_reads_key_value_lines() {
+        let path = std::env::temp_dir().join(format!("trait-examples-config-{}.conf", std::process::id()));
+        fs::write(&path, "# app settings\n\n  port = 9090  \ndebug=false\n").unwrap();
+        let mut app = Application::new();
+        app.set_config("debug", "true".to_string());
+        app.set_config("name", "demo".to_string());
+        
+        let result = app.load_from_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(app.get_config("port").map(String::as_str), Some("9090"));
+        // File values replace existing ones, and keys the file doesn't mention are kept
+        assert_eq!(app.get_config("debug").map(String::as_str), Some("false"));
+        assert_eq!(app.get_config("name").map(String::as_str), Some("demo"));
+        assert_eq!(app.config.len(), 3);
+    }
+    
+    #[test]
+    fn load_from_file_reports_missing_and_malformed_files() {
+        let mut app = Application::new();
+        let missing = std::env::temp_dir().join("trait-examples-no-such-config.conf");
+        let missing = missing.to_str().unwrap();
+        assert!(matches!(app.load_from_file(missing), Err(ConfigError::NotFound(path)) if path == missing));
+        
+        let path = std::env::temp_dir().join(format!("trait-examples-bad-config-{}.conf", std::process::id()));
+        fs::write(&path, "port = 9090\njust some words\n").unwrap();
+        let result = app.load_from_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ConfigError::Parse { line: 2, .. })));
+        // Nothing is applied from a file with a bad line
+        assert!(app.config.is_empty());
+    }
+    
+    #[test]
+    fn task_runner_interleaves_tasks_until_all_are_ready() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut runner = TaskRunner::new();
+        runner.spawn(CountdownTask { name: "short".to_string(), remaining: 1, log: Rc::clone(&log) });
+        runner.spawn(CountdownTask { name: "long".to_string(), remaining: 3, log: Rc::clone(&log) });
+        
+        assert_eq!(runner.run(), 4);
+        assert_eq!(*log.borrow(), ["short 1", "long 3", "short done", "long 2", "long 1", "long done"]);
+        // Finished tasks are dropped, so running again does nothing
+        assert_eq!(runner.run(), 0);
+        assert_eq!(TaskRunner::new().run(), 0);
+    }
+    
+    #[test]
+    fn env_overrides_win_over_existing_values() {
+        // A prefix no other test uses, since the environment is shared between test threads
+        std::env::set_var("TRAITTEST_PORT", "9090");
+        std::env::set_var("TRAITTEST_LOG_LEVEL", "debug");
+        std::env::set_var("TRAITTEST_", "ignored");
+        let mut app = Application::new();
+        app.set_config("port", "8080".to_string());
+        app.set_config("debug", "true".to_string());
+        
+        app.apply_env_overrides("TRAITTEST");
+        assert_eq!(app.get_config("port").map(String::as_str), Some("9090"));
+        assert_eq!(app.get_config("log_level").map(String::as_str), Some("debug"));
+        assert_eq!(app.get_config("debug").map(String::as_str), Some("true"));
+        assert_eq!(app.config.len(), 3);
+        
+        std::env::remove_var("TRAITTEST_PORT");
+        std::env::remove_var("TRAITTEST_LOG_LEVEL");
+        std::env::remove_var("TRAITTEST_");
+    }
+    
+    #[test]
+    fn civil_dates_and_the_current_year() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        
+        let year = current_year();
+        assert_eq!(now_string()[..4], year.to_string());
+        assert!(year >= 2024);
+    }
+}
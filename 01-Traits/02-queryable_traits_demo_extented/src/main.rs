@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Read};
+
 // Define the User struct first
 #[derive(Debug, Clone)]
 struct User {
     name: String,
     age: u32,
+    // Bumped on every update so concurrent edits can be detected
+    version: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -12,6 +18,31 @@ struct Product {
     category: String,
 }
 
+// Orders f64s totally so they can be used as sort keys. Follows f64::total_cmp: NaN sorts
+// after every number (or before, for a negative NaN), and -0.0 sorts before 0.0.
+#[derive(Debug, Clone, Copy)]
+struct OrderedFloat(f64);
+
+impl PartialEq for OrderedFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for OrderedFloat {}
+
+impl PartialOrd for OrderedFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedFloat {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
 // 1. Basic Queryable Trait
 trait Queryable<T> {
     fn find_by_id(&self, id: u32) -> Option<&T>;
@@ -21,6 +52,80 @@ trait Queryable<T> {
     fn count(&self) -> usize {
         self.find_all().len()
     }
+    
+    // All items ordered by `key`; items with equal keys keep their find_all order
+    fn find_sorted_by<K: Ord, F: Fn(&T) -> K>(&self, key: F) -> Vec<&T> {
+        let mut items = self.find_all();
+        items.sort_by_key(|item| key(item));
+        items
+    }
+    
+    fn find_sorted_by_desc<K: Ord, F: Fn(&T) -> K>(&self, key: F) -> Vec<&T> {
+        let mut items = self.find_all();
+        items.sort_by_key(|item| std::cmp::Reverse(key(item)));
+        items
+    }
+    
+    // Only valid when find_all returns items sorted by key_fn
+    fn binary_find<K: Ord, F: Fn(&T) -> K>(&self, key_fn: F, target: K) -> Option<&T> {
+        let items = self.find_all();
+        items
+            .binary_search_by(|item| key_fn(item).cmp(&target))
+            .ok()
+            .map(|index| items[index])
+    }
+    
+    // Every item with the id find_by_id knows it by, in find_all order. Ids default to
+    // positions, which is what the Vec-backed repositories use; repositories with their own ids
+    // should override this.
+    fn find_all_with_ids(&self) -> Vec<(u32, &T)> {
+        self.find_all().into_iter().enumerate().map(|(i, item)| (i as u32, item)).collect()
+    }
+    
+    // Cursor-based paging: returns up to `limit` items whose id comes after `cursor` (from the
+    // start when None), plus the cursor for the next page or None once everything has been
+    // returned. The cursor is the id of the last item returned, so inserting or deleting items
+    // between pages doesn't skip or repeat anything, as long as find_all_with_ids lists ids in
+    // ascending order. A limit of 0 returns nothing.
+    fn page_after(&self, cursor: Option<u32>, limit: usize) -> (Vec<&T>, Option<u32>) {
+        if limit == 0 {
+            return (Vec::new(), None);
+        }
+        let entries = self.find_all_with_ids();
+        // The cursor's own item may have been deleted since, so look for the first id past it
+        let start = cursor.map_or(0, |cursor| entries.partition_point(|&(id, _)| id <= cursor));
+        let page = &entries[start..(start + limit).min(entries.len())];
+        let next_cursor = if start + page.len() < entries.len() {
+            page.last().map(|&(id, _)| id)
+        } else {
+            None
+        };
+        (page.iter().map(|&(_, item)| item).collect(), next_cursor)
+    }
+    
+    // Offset paging. Pages are numbered from 1; page 0 and pages past the end come back empty
+    // but still report the totals. A per_page of 0 is treated as 1.
+    fn paginate(&self, page: usize, per_page: usize) -> Page<&T> {
+        let per_page = per_page.max(1);
+        let items = self.find_all();
+        let total = items.len();
+        let start = page.saturating_sub(1).saturating_mul(per_page);
+        let page_items = if page == 0 {
+            Vec::new()
+        } else {
+            items.into_iter().skip(start).take(per_page).collect()
+        };
+        Page { items: page_items, total, page, total_pages: total.div_ceil(per_page) }
+    }
+}
+
+// One page of query results, with enough information to render page links
+#[derive(Debug)]
+struct Page<T> {
+    items: Vec<T>,
+    total: usize,
+    page: usize,
+    total_pages: usize,
 }
 
 // 2. Basic UserRepository
@@ -32,9 +137,9 @@ impl UserRepository {
     fn new() -> Self {
         Self {
             users: vec![
-                User { name: "Alice".to_string(), age: 30 },
-                User { name: "Bob".to_string(), age: 25 },
-                User { name: "Charlie".to_string(), age: 35 },
+                User { name: "Alice".to_string(), age: 30, version: 0 },
+                User { name: "Bob".to_string(), age: 25, version: 0 },
+                User { name: "Charlie".to_string(), age: 35, version: 0 },
             ]
         }
     }
@@ -119,77 +224,248 @@ trait QueryableExt<T>: Queryable<T> {
     {
         self.filter(predicate).len()
     }
+    
+    // Numeric aggregates. An empty repository sums to 0.0; the others return None for it
+    fn sum_by<F: Fn(&T) -> f64>(&self, f: F) -> f64 {
+        // fold rather than sum: an empty f64 sum is -0.0
+        self.find_all().into_iter().map(f).fold(0.0, |total, value| total + value)
+    }
+    
+    fn avg_by<F: Fn(&T) -> f64>(&self, f: F) -> Option<f64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        Some(self.sum_by(f) / count as f64)
+    }
+    
+    // The first item with the smallest key
+    fn min_by_key<K: Ord, F: Fn(&T) -> K>(&self, f: F) -> Option<&T> {
+        self.find_all().into_iter().min_by_key(|item| f(item))
+    }
+    
+    // The last item with the largest key
+    fn max_by_key<K: Ord, F: Fn(&T) -> K>(&self, f: F) -> Option<&T> {
+        self.find_all().into_iter().max_by_key(|item| f(item))
+    }
+    
+    // Items in each group keep their find_all order
+    fn group_by<K: Eq + Hash, F: Fn(&T) -> K>(&self, key: F) -> HashMap<K, Vec<&T>> {
+        let mut groups: HashMap<K, Vec<&T>> = HashMap::new();
+        for item in self.find_all() {
+            groups.entry(key(item)).or_default().push(item);
+        }
+        groups
+    }
 }
 
 // Blanket implementation for all Queryable types
 impl<T, Q: Queryable<T>> QueryableExt<T> for Q {}
 
 // 5. Advanced UserRepository with CRUD operations
+// A single repository edit, so edits can be recorded and replayed as data
+#[derive(Debug, Clone)]
+enum RepoCommand {
+    Insert(User),
+    // User id, the version the edit was based on, and the new user data
+    Update(u32, u64, User),
+    Delete(u32),
+}
+
+// Every user keeps the id it was given on insert, so deleting one never renumbers the others.
+// `index` maps each id to its position in `users` so lookups by id stay O(1). That costs an
+// extra map entry (a u32 id and a usize position, plus hashing overhead) per user, and makes
+// deletes O(n), since they have to shift the positions of every later user.
 struct AdvancedUserRepository {
-    users: Vec<User>,
+    users: Vec<(u32, User)>,
+    index: HashMap<u32, usize>,
     next_id: u32,
 }
 
 impl AdvancedUserRepository {
     fn new() -> Self {
-        Self {
-            users: vec![
-                User { name: "Alice".to_string(), age: 30 },
-                User { name: "Bob".to_string(), age: 25 },
-                User { name: "Charlie".to_string(), age: 35 },
-            ],
-            next_id: 3,
-        }
+        let mut repo = Self { users: Vec::new(), index: HashMap::new(), next_id: 0 };
+        repo.insert(User { name: "Alice".to_string(), age: 30, version: 0 });
+        repo.insert(User { name: "Bob".to_string(), age: 25, version: 0 });
+        repo.insert(User { name: "Charlie".to_string(), age: 35, version: 0 });
+        repo
     }
     
     fn insert(&mut self, user: User) -> u32 {
         let id = self.next_id;
-        self.users.push(user);
+        self.index.insert(id, self.users.len());
+        self.users.push((id, user));
         self.next_id += 1;
         id
     }
     
-    fn update<F>(&mut self, id: u32, updater: F) -> bool
+    // Reserves room for every user up front so large imports don't reallocate repeatedly.
+    // Returns the assigned ids in the same order as `users`.
+    fn insert_many(&mut self, users: Vec<User>) -> Vec<u32> {
+        self.users.reserve(users.len());
+        self.index.reserve(users.len());
+        users.into_iter().map(|user| self.insert(user)).collect()
+    }
+    
+    // Reads `name,age` rows one line at a time, skipping the header, and returns how many users
+    // were imported. Rows before a malformed one have already been inserted when it is reported.
+    fn import_csv<R: Read>(&mut self, reader: R) -> Result<usize, String> {
+        let mut imported = 0;
+        for (index, line) in BufReader::new(reader).lines().enumerate().skip(1) {
+            let line_number = index + 1;
+            let line = line.map_err(|e| format!("line {}: {}", line_number, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, age) = line.split_once(',')
+                .ok_or_else(|| format!("line {}: expected name,age but got {:?}", line_number, line))?;
+            let age = age.trim().parse()
+                .map_err(|e| format!("line {}: invalid age {:?}: {}", line_number, age.trim(), e))?;
+            self.insert(User { name: name.trim().to_string(), age, version: 0 });
+            imported += 1;
+        }
+        Ok(imported)
+    }
+    
+    // Optimistic locking: the update only goes through if nobody else has changed the user
+    // since `expected_version` was read
+    fn update<F>(&mut self, id: u32, expected_version: u64, updater: F) -> Result<(), String>
     where 
         F: FnOnce(&mut User),
     {
-        if let Some(user) = self.users.get_mut(id as usize) {
-            updater(user);
-            true
-        } else {
-            false
+        let user = self.find_by_id_mut(id)
+            .ok_or_else(|| format!("Cannot update user {}: not found", id))?;
+        if user.version != expected_version {
+            return Err(format!(
+                "Cannot update user {}: expected version {} but found {}",
+                id, expected_version, user.version
+            ));
         }
+        updater(user);
+        user.version = expected_version + 1;
+        Ok(())
     }
     
     fn delete(&mut self, id: u32) -> bool {
-        if (id as usize) < self.users.len() {
-            self.users.remove(id as usize);
-            true
-        } else {
-            false
+        let Some(position) = self.index.remove(&id) else {
+            return false;
+        };
+        self.users.remove(position);
+        for (user_id, _) in &self.users[position..] {
+            if let Some(shifted) = self.index.get_mut(user_id) {
+                *shifted -= 1;
+            }
         }
+        true
+    }
+    
+    fn find_by_id_mut(&mut self, id: u32) -> Option<&mut User> {
+        let position = *self.index.get(&id)?;
+        self.users.get_mut(position).map(|(_, user)| user)
+    }
+    
+    // Users with their ids, in insertion order
+    fn entries(&self) -> impl Iterator<Item = (u32, &User)> {
+        self.users.iter().map(|(id, user)| (*id, user))
+    }
+    
+    fn apply(&mut self, cmd: RepoCommand) -> Result<(), String> {
+        match cmd {
+            RepoCommand::Insert(user) => {
+                self.insert(user);
+                Ok(())
+            }
+            RepoCommand::Update(id, expected_version, new_user) => {
+                self.update(id, expected_version, |user| *user = new_user)
+            }
+            RepoCommand::Delete(id) => {
+                if self.delete(id) {
+                    Ok(())
+                } else {
+                    Err(format!("Cannot delete user {}: not found", id))
+                }
+            }
+        }
+    }
+    
+    // Applies commands in order, stopping at the first one that fails
+    fn apply_all(&mut self, cmds: Vec<RepoCommand>) -> Result<(), String> {
+        cmds.into_iter().try_for_each(|cmd| self.apply(cmd))
     }
 }
 
 impl Queryable<User> for AdvancedUserRepository {
     fn find_by_id(&self, id: u32) -> Option<&User> {
-        self.users.get(id as usize)
+        let position = *self.index.get(&id)?;
+        self.users.get(position).map(|(_, user)| user)
     }
     
     fn find_all(&self) -> Vec<&User> {
-        self.users.iter().collect()
+        self.users.iter().map(|(_, user)| user).collect()
     }
     
     fn filter<F>(&self, predicate: F) -> Vec<&User> 
     where 
         F: Fn(&User) -> bool 
     {
-        self.users.iter().filter(|user| predicate(user)).collect()
+        self.users.iter().map(|(_, user)| user).filter(|user| predicate(user)).collect()
     }
     
     fn count(&self) -> usize {
         self.users.len()
     }
+    
+    // Ids are handed out in increasing order and users stay in insertion order
+    fn find_all_with_ids(&self) -> Vec<(u32, &User)> {
+        self.entries().collect()
+    }
+}
+
+// Wraps any state so changes can be undone and redone. Each change is applied through `mutate`
+// with a matching `backward` closure that reverts it; both are kept so the change can be redone.
+// Making a new change after undoing discards the redo history.
+type Mutation<T> = Box<dyn Fn(&mut T)>;
+
+struct Undoable<T> {
+    state: T,
+    undo_stack: Vec<(Mutation<T>, Mutation<T>)>,
+    redo_stack: Vec<(Mutation<T>, Mutation<T>)>,
+}
+
+impl<T> Undoable<T> {
+    fn new(state: T) -> Self {
+        Self { state, undo_stack: Vec::new(), redo_stack: Vec::new() }
+    }
+    
+    fn state(&self) -> &T {
+        &self.state
+    }
+    
+    fn mutate(&mut self, forward: impl Fn(&mut T) + 'static, backward: impl Fn(&mut T) + 'static) {
+        forward(&mut self.state);
+        self.undo_stack.push((Box::new(forward), Box::new(backward)));
+        self.redo_stack.clear();
+    }
+    
+    // Reverts the most recent change; false if there was nothing to undo
+    fn undo(&mut self) -> bool {
+        let Some((forward, backward)) = self.undo_stack.pop() else {
+            return false;
+        };
+        backward(&mut self.state);
+        self.redo_stack.push((forward, backward));
+        true
+    }
+    
+    // Re-applies the most recently undone change; false if there was nothing to redo
+    fn redo(&mut self) -> bool {
+        let Some((forward, backward)) = self.redo_stack.pop() else {
+            return false;
+        };
+        forward(&mut self.state);
+        self.undo_stack.push((forward, backward));
+        true
+    }
 }
 
 // 6. Generic helper functions
@@ -257,6 +533,59 @@ fn demonstrate_product_queries() {
     for product in electronics {
         println!("  - {} (${:.2})", product.name, product.price);
     }
+    
+    let by_category = product_repo.group_by(|p| p.category.clone());
+    let mut categories: Vec<&String> = by_category.keys().collect();
+    categories.sort();
+    for category in categories {
+        let names: Vec<&str> = by_category[category].iter().map(|p| p.name.as_str()).collect();
+        println!("{}: {:?}", category, names);
+    }
+    
+    let by_price: Vec<&str> = product_repo.find_sorted_by_desc(|p| OrderedFloat(p.price))
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    println!("Most expensive first: {:?}", by_price);
+    
+    println!("Total value: ${:.2}", product_repo.sum_by(|p| p.price));
+    if let Some(average) = product_repo.avg_by(|p| p.price) {
+        println!("Average price: ${:.2}", average);
+    }
+    if let (Some(cheapest), Some(priciest)) = (
+        product_repo.min_by_key(|p| OrderedFloat(p.price)),
+        product_repo.max_by_key(|p| OrderedFloat(p.price)),
+    ) {
+        println!("Cheapest: {}, priciest: {}", cheapest.name, priciest.name);
+    }
+    let empty_repo = ProductRepository { products: Vec::new() };
+    println!("Empty repository: sum {:.2}, average {:?}, cheapest {:?}",
+             empty_repo.sum_by(|p| p.price),
+             empty_repo.avg_by(|p| p.price),
+             empty_repo.min_by_key(|p| OrderedFloat(p.price)).map(|p| &p.name));
+    
+    let user_repo = UserRepository::new();
+    let by_age: Vec<&str> = user_repo.find_sorted_by(|u| u.age).iter().map(|u| u.name.as_str()).collect();
+    println!("Users youngest first: {:?}", by_age);
+    println!();
+}
+
+fn demonstrate_binary_search() {
+    println!("=== Binary Search Demo ===");
+    // binary_find needs the repository sorted by the search key
+    let mut product_repo = ProductRepository::new();
+    product_repo.products.sort_by(|a, b| a.price.total_cmp(&b.price));
+    
+    // Prices aren't Ord, so search by whole cents
+    let price_in_cents = |p: &Product| (p.price * 100.0).round() as u64;
+    match product_repo.binary_find(price_in_cents, 69999) {
+        Some(product) => println!("Product costing $699.99: {}", product.name),
+        None => println!("No product costs $699.99"),
+    }
+    match product_repo.binary_find(price_in_cents, 500) {
+        Some(product) => println!("Product costing $5.00: {}", product.name),
+        None => println!("No product costs $5.00"),
+    }
     println!();
 }
 
@@ -300,12 +629,49 @@ fn demonstrate_extended_queries() {
     println!();
 }
 
+fn demonstrate_cursor_paging() {
+    println!("=== Cursor Paging Demo ===");
+    let mut repo = AdvancedUserRepository::new();
+    repo.insert(User { name: "David".to_string(), age: 28, version: 0 });
+    
+    let mut cursor = None;
+    let mut page_number = 1;
+    loop {
+        let (page, next_cursor) = repo.page_after(cursor, 2);
+        let names: Vec<&str> = page.iter().map(|u| u.name.as_str()).collect();
+        println!("Page {} (after {:?}): {:?}", page_number, cursor, names);
+        match next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+        // Edits between pages don't shift the cursor, which is an id rather than a position
+        if page_number == 1 {
+            repo.delete(0);
+            repo.insert(User { name: "Eve".to_string(), age: 22, version: 0 });
+            println!("Deleted Alice and inserted Eve");
+        }
+        page_number += 1;
+    }
+    println!();
+}
+
+fn demonstrate_pagination() {
+    println!("=== Pagination Demo ===");
+    let repo = UserRepository::new();
+    for page_number in 1..=3 {
+        let page = repo.paginate(page_number, 2);
+        let names: Vec<&str> = page.items.iter().map(|u| u.name.as_str()).collect();
+        println!("Page {}/{} ({} users in total): {:?}", page.page, page.total_pages, page.total, names);
+    }
+    println!();
+}
+
 fn demonstrate_crud_operations() {
     println!("=== CRUD Operations Demo ===");
     let mut repo = AdvancedUserRepository::new();
     
     // Create
-    let new_id = repo.insert(User { name: "David".to_string(), age: 28 });
+    let new_id = repo.insert(User { name: "David".to_string(), age: 28, version: 0 });
     println!("Inserted user with ID: {}", new_id);
     
     // Read
@@ -314,11 +680,17 @@ fn demonstrate_crud_operations() {
     }
     
     // Update
-    let updated = repo.update(new_id, |user| {
+    let version = repo.find_by_id(new_id).map_or(0, |user| user.version);
+    let updated = repo.update(new_id, version, |user| {
         user.age += 1;
         println!("Updated {}'s age to {}", user.name, user.age);
     });
-    println!("Update successful: {}", updated);
+    println!("Update successful: {}", updated.is_ok());
+    
+    // A second edit based on the same (now stale) version is rejected
+    if let Err(e) = repo.update(new_id, version, |user| user.age += 1) {
+        println!("Concurrent update rejected: {}", e);
+    }
     
     // Query after update
     if let Some(user) = repo.find_by_id(new_id) {
@@ -331,19 +703,110 @@ fn demonstrate_crud_operations() {
     
     // Show all users
     println!("All users in advanced repository:");
-    for (i, user) in repo.find_all().iter().enumerate() {
-        println!("  [{}] {} (age: {})", i, user.name, user.age);
+    for (id, user) in repo.entries() {
+        println!("  [{}] {} (age: {})", id, user.name, user.age);
+    }
+    
+    println!();
+}
+
+fn demonstrate_repo_commands() {
+    println!("=== Repository Commands Demo ===");
+    let mut repo = AdvancedUserRepository::new();
+    
+    let script = vec![
+        RepoCommand::Insert(User { name: "Eve".to_string(), age: 22, version: 0 }),
+        RepoCommand::Update(0, 0, User { name: "Alice".to_string(), age: 31, version: 0 }),
+        RepoCommand::Delete(1),
+    ];
+    match repo.apply_all(script) {
+        Ok(()) => println!("Script applied successfully"),
+        Err(e) => println!("Script failed: {}", e),
+    }
+    
+    // Deleting Bob (id 1) leaves everyone else with the id they were inserted with
+    for (id, user) in repo.entries() {
+        println!("  [{}] {} (age: {})", id, user.name, user.age);
+    }
+    if let Some(user) = repo.find_by_id(2) {
+        println!("User 2 is still {}", user.name);
+    }
+    
+    // Commands that reference a missing user are rejected
+    if let Err(e) = repo.apply(RepoCommand::Delete(42)) {
+        println!("Expected failure: {}", e);
     }
     
     println!();
 }
 
+fn demonstrate_undo() {
+    println!("=== Undo Demo ===");
+    let mut users = Undoable::new(UserRepository::new().users);
+    let names = |users: &Undoable<Vec<User>>| -> Vec<String> {
+        users.state().iter().map(|u| format!("{} ({})", u.name, u.age)).collect()
+    };
+    println!("Start: {:?}", names(&users));
+    
+    users.mutate(
+        |users| users.push(User { name: "Zoe".to_string(), age: 19, version: 0 }),
+        |users| { users.pop(); },
+    );
+    users.mutate(|users| users[0].age += 1, |users| users[0].age -= 1);
+    println!("After two changes: {:?}", names(&users));
+    
+    while users.undo() {}
+    println!("After undoing both: {:?}", names(&users));
+    users.redo();
+    println!("After one redo: {:?}", names(&users));
+    println!();
+}
+
+fn demonstrate_bulk_import() {
+    println!("=== Bulk Import Demo ===");
+    let mut repo = AdvancedUserRepository::new();
+    
+    let users = (0..10_000)
+        .map(|i| User { name: format!("Imported{}", i), age: 18 + i % 50, version: 0 })
+        .collect();
+    let ids = repo.insert_many(users);
+    println!("Inserted {} users with IDs {}..={}", ids.len(), ids[0], ids[ids.len() - 1]);
+    println!("Repository now holds {} users", repo.count());
+    
+    // Lookups go through the id index, so they stay fast however many users there are
+    repo.delete(ids[0]);
+    let start = std::time::Instant::now();
+    let found = repo.find_by_id(ids[7_500]).map(|user| user.name.clone());
+    println!("User {} is {:?} (lookup took {:?})", ids[7_500], found, start.elapsed());
+    
+    let csv = "name,age\nFrank,41\nGrace,29\n";
+    match repo.import_csv(csv.as_bytes()) {
+        Ok(imported) => println!("Imported {} users from CSV", imported),
+        Err(e) => println!("CSV import failed: {}", e),
+    }
+    
+    let malformed_csv = "name,age\nHeidi,33\nIvan,unknown\nJudy,27\n";
+    match repo.import_csv(malformed_csv.as_bytes()) {
+        Ok(imported) => println!("Imported {} users from CSV", imported),
+        Err(e) => println!("CSV import failed: {}", e),
+    }
+    println!("Repository now holds {} users", repo.count());
+    
+    println!();
+}
+
 fn main() {
     demonstrate_basic_queries();
     demonstrate_product_queries();
+    demonstrate_binary_search();
     demonstrate_generic_queries();
     demonstrate_extended_queries();
     demonstrate_crud_operations();
+    demonstrate_cursor_paging();
+    demonstrate_pagination();
+    demonstrate_repo_commands();
+    demonstrate_bulk_import();
+    demonstrate_undo();
     
     println!("=== Summary ===");
     println!("The Queryable trait demonstrates:");
@@ -354,3 +817,261 @@ fn main() {
     println!("5. CRUD operations building on the query foundation");
     println!("6. Zero-cost abstractions with compile-time optimisation");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn user(name: &str, age: u32) -> User {
+        User { name: name.to_string(), age, version: 0 }
+    }
+    
+    fn names_with_ids(repo: &AdvancedUserRepository) -> Vec<(u32, String)> {
+        repo.entries().map(|(id, user)| (id, user.name.clone())).collect()
+    }
+    
+    #[test]
+    fn apply_all_runs_a_batch_of_commands_in_order() {
+        let mut repo = AdvancedUserRepository::new();
+        let script = vec![
+            RepoCommand::Insert(user("Eve", 22)),
+            RepoCommand::Update(0, 0, user("Alicia", 31)),
+            RepoCommand::Delete(1),
+        ];
+        assert_eq!(repo.apply_all(script), Ok(()));
+        
+        assert_eq!(names_with_ids(&repo), [
+            (0, "Alicia".to_string()),
+            (2, "Charlie".to_string()),
+            (3, "Eve".to_string()),
+        ]);
+        assert_eq!(repo.find_by_id(0).map(|u| u.age), Some(31));
+    }
+    
+    #[test]
+    fn apply_all_stops_at_the_first_failing_command() {
+        let mut repo = AdvancedUserRepository::new();
+        let script = vec![
+            RepoCommand::Delete(0),
+            RepoCommand::Delete(42),
+            RepoCommand::Insert(user("Eve", 22)),
+        ];
+        assert!(repo.apply_all(script).is_err());
+        assert_eq!(repo.count(), 2);
+        assert!(repo.filter(|u| u.name == "Eve").is_empty());
+    }
+    
+    #[test]
+    fn second_update_with_the_same_version_is_rejected() {
+        let mut repo = AdvancedUserRepository::new();
+        assert_eq!(repo.update(1, 0, |u| u.age = 26), Ok(()));
+        assert!(repo.update(1, 0, |u| u.age = 99).is_err());
+        
+        let bob = repo.find_by_id(1).unwrap();
+        assert_eq!((bob.age, bob.version), (26, 1));
+        // Editing the latest version works again
+        assert_eq!(repo.update(1, 1, |u| u.age = 27), Ok(()));
+    }
+    
+    #[test]
+    fn binary_find_looks_up_products_by_exact_price() {
+        let mut repo = ProductRepository::new();
+        repo.products.sort_by(|a, b| a.price.total_cmp(&b.price));
+        let cents = |p: &Product| (p.price * 100.0).round() as u64;
+        
+        assert_eq!(repo.binary_find(cents, 69999).map(|p| p.name.as_str()), Some("Phone"));
+        assert_eq!(repo.binary_find(cents, 299).map(|p| p.name.as_str()), Some("Pen"));
+        assert!(repo.binary_find(cents, 500).is_none());
+    }
+    
+    fn page_names(page: &[&User]) -> Vec<String> {
+        page.iter().map(|u| u.name.clone()).collect()
+    }
+    
+    #[test]
+    fn cursor_pages_through_every_user_and_terminates() {
+        let mut repo = AdvancedUserRepository::new();
+        repo.insert(user("David", 28));
+        
+        let (first, cursor) = repo.page_after(None, 2);
+        assert_eq!(page_names(&first), ["Alice", "Bob"]);
+        assert_eq!(cursor, Some(1));
+        
+        let (second, cursor) = repo.page_after(cursor, 2);
+        assert_eq!(page_names(&second), ["Charlie", "David"]);
+        assert_eq!(cursor, None);
+        
+        let (empty, cursor) = repo.page_after(None, 0);
+        assert!(empty.is_empty());
+        assert_eq!(cursor, None);
+    }
+    
+    #[test]
+    fn cursor_is_stable_when_users_are_inserted_and_deleted() {
+        let mut repo = AdvancedUserRepository::new();
+        let (first, cursor) = repo.page_after(None, 2);
+        assert_eq!(page_names(&first), ["Alice", "Bob"]);
+        
+        // Deleting a user before the cursor, and the cursor's own user, skips nobody
+        repo.delete(0);
+        repo.delete(1);
+        repo.insert(user("Eve", 22));
+        let (second, cursor) = repo.page_after(cursor, 2);
+        assert_eq!(page_names(&second), ["Charlie", "Eve"]);
+        assert_eq!(cursor, None);
+    }
+    
+    #[test]
+    fn positional_repositories_use_positions_as_cursors() {
+        let repo = ProductRepository::new();
+        let (page, cursor) = repo.page_after(Some(1), 1);
+        assert_eq!(page.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["Phone"]);
+        assert_eq!(cursor, Some(2));
+    }
+    
+    #[test]
+    fn insert_many_assigns_ids_in_order() {
+        let mut repo = AdvancedUserRepository::new();
+        let users = (0..100).map(|i| user(&format!("User {}", i), 20 + i % 50)).collect();
+        let ids = repo.insert_many(users);
+        
+        // The three demo users already have ids 0 to 2
+        assert_eq!(ids, (3..103).collect::<Vec<u32>>());
+        assert_eq!(repo.count(), 103);
+        assert_eq!(repo.find_by_id(102).map(|u| u.name.as_str()), Some("User 99"));
+    }
+    
+    #[test]
+    fn import_csv_reports_the_malformed_line() {
+        let mut repo = AdvancedUserRepository::new();
+        let csv = "name,age\nDan,41\nEve, 22\n\nFrank,old\nGrace,29\n";
+        assert_eq!(repo.import_csv(csv.as_bytes()), Err("line 5: invalid age \"old\": invalid digit found in string".to_string()));
+        // Rows before the bad one were imported, rows after it weren't
+        assert_eq!(repo.count(), 5);
+        assert_eq!(repo.find_by_id(4).map(|u| (u.name.as_str(), u.age)), Some(("Eve", 22)));
+        
+        let mut repo = AdvancedUserRepository::new();
+        assert_eq!(repo.import_csv("name,age\nHank\n".as_bytes()), Err("line 2: expected name,age but got \"Hank\"".to_string()));
+        assert_eq!(repo.import_csv("name,age\nIvy,33\nJon,44".as_bytes()), Ok(2));
+    }
+    
+    #[test]
+    fn paginate_three_users_into_pages_of_two() {
+        let repo = UserRepository::new();
+        let first = repo.paginate(1, 2);
+        assert_eq!(page_names(&first.items), ["Alice", "Bob"]);
+        assert_eq!((first.total, first.page, first.total_pages), (3, 1, 2));
+        
+        let second = repo.paginate(2, 2);
+        assert_eq!(page_names(&second.items), ["Charlie"]);
+        assert_eq!(second.total_pages, 2);
+        
+        let past_the_end = repo.paginate(3, 2);
+        assert!(past_the_end.items.is_empty());
+        assert_eq!((past_the_end.total, past_the_end.page), (3, 3));
+        assert!(repo.paginate(0, 2).items.is_empty());
+        
+        // per_page 0 is treated as 1
+        let single = repo.paginate(2, 0);
+        assert_eq!(page_names(&single.items), ["Bob"]);
+        assert_eq!(single.total_pages, 3);
+    }
+    
+    #[test]
+    fn sorts_users_by_age_and_products_by_price_descending() {
+        let users = UserRepository::new();
+        assert_eq!(page_names(&users.find_sorted_by(|u| u.age)), ["Bob", "Alice", "Charlie"]);
+        
+        let products = ProductRepository::new();
+        let by_price = products.find_sorted_by_desc(|p| OrderedFloat(p.price));
+        assert_eq!(by_price.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["Laptop", "Phone", "Book", "Pen"]);
+    }
+    
+    #[test]
+    fn deleting_the_middle_user_keeps_the_others_ids() {
+        let mut repo = AdvancedUserRepository::new();
+        assert!(repo.delete(1));
+        
+        assert_eq!(names_with_ids(&repo), [(0, "Alice".to_string()), (2, "Charlie".to_string())]);
+        assert!(repo.find_by_id(1).is_none());
+        assert_eq!(repo.find_by_id(2).map(|u| u.name.as_str()), Some("Charlie"));
+        assert!(repo.update(2, 0, |u| u.age += 1).is_ok());
+        assert_eq!(repo.find_by_id(2).map(|u| u.age), Some(36));
+        
+        assert!(!repo.delete(1));
+        // New users never reuse a deleted id
+        assert_eq!(repo.insert(user("Dan", 41)), 3);
+    }
+    
+    #[test]
+    fn index_finds_users_among_ten_thousand() {
+        let mut repo = AdvancedUserRepository::new();
+        let ids = repo.insert_many((0..10_000).map(|i| user(&format!("User {}", i), i % 90)).collect());
+        // Deleting shifts positions, which the index has to follow
+        for id in ids.iter().step_by(100) {
+            assert!(repo.delete(*id));
+        }
+        
+        assert_eq!(repo.find_by_id(ids[5_001]).map(|u| u.name.as_str()), Some("User 5001"));
+        assert_eq!(repo.find_by_id(ids[9_999]).map(|u| u.name.as_str()), Some("User 9999"));
+        assert!(repo.find_by_id(ids[7_000]).is_none());
+        assert_eq!(repo.count(), 3 + 10_000 - 100);
+        assert!(repo.entries().all(|(id, u)| repo.find_by_id(id).map(|found| &found.name) == Some(&u.name)));
+    }
+    
+    #[test]
+    fn product_price_aggregates() {
+        let products = ProductRepository::new();
+        assert!((products.sum_by(|p| p.price) - 1722.96).abs() < 1e-9);
+        assert!((products.avg_by(|p| p.price).unwrap() - 1722.96 / 4.0).abs() < 1e-9);
+        assert_eq!(products.min_by_key(|p| OrderedFloat(p.price)).map(|p| p.name.as_str()), Some("Pen"));
+        assert_eq!(products.max_by_key(|p| OrderedFloat(p.price)).map(|p| p.name.as_str()), Some("Laptop"));
+    }
+    
+    #[test]
+    fn empty_repository_aggregates() {
+        let empty = ProductRepository { products: Vec::new() };
+        let sum = empty.sum_by(|p| p.price);
+        assert_eq!(sum, 0.0);
+        assert!(sum.is_sign_positive());
+        assert_eq!(empty.avg_by(|p| p.price), None);
+        assert!(empty.min_by_key(|p| OrderedFloat(p.price)).is_none());
+        assert!(empty.max_by_key(|p| OrderedFloat(p.price)).is_none());
+    }
+    
+    #[test]
+    fn group_products_by_category() {
+        let products = ProductRepository::new();
+        let groups = products.group_by(|p| p.category.clone());
+        let names = |category: &str| -> Vec<&str> {
+            groups[category].iter().map(|p| p.name.as_str()).collect()
+        };
+        
+        assert_eq!(groups.len(), 3);
+        assert_eq!(names("Electronics"), ["Laptop", "Phone"]);
+        assert_eq!(names("Education"), ["Book"]);
+        assert_eq!(names("Office"), ["Pen"]);
+    }
+    
+    #[test]
+    fn undoable_reverts_mutations_in_reverse_order() {
+        let mut list = Undoable::new(vec![1, 2]);
+        list.mutate(|v| v.push(3), |v| { v.pop(); });
+        list.mutate(|v| v[0] = 10, |v| v[0] = 1);
+        assert_eq!(list.state(), &[10, 2, 3]);
+        
+        assert!(list.undo());
+        assert_eq!(list.state(), &[1, 2, 3]);
+        assert!(list.undo());
+        assert_eq!(list.state(), &[1, 2]);
+        assert!(!list.undo());
+        
+        assert!(list.redo());
+        assert_eq!(list.state(), &[1, 2, 3]);
+        // A new change discards what's left to redo
+        list.mutate(|v| v.clear(), |v| v.extend([1, 2, 3]));
+        assert!(!list.redo());
+        assert!(list.undo());
+        assert_eq!(list.state(), &[1, 2, 3]);
+    }
+}
@@ -1,5 +1,341 @@
+mod particles;
+mod shake;
+mod state_machine;
+
 use macroquad::prelude::*;
 
+use particles::ParticleSystem;
+use shake::ScreenShake;
+use state_machine::StateMachine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlayerState {
+    Idle,
+    Walking,
+    Jumping,
+    Falling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlayerEvent {
+    StartMoving,
+    StopMoving,
+    Jump,
+    Fall,
+    Land,
+}
+
+/// How the player moves between standing, walking and being in the air
+fn player_state_machine() -> StateMachine<PlayerState, PlayerEvent> {
+    use PlayerEvent::*;
+    use PlayerState::*;
+
+    let mut machine = StateMachine::new(Idle);
+    machine.add_transition(Idle, StartMoving, Walking);
+    machine.add_transition(Walking, StopMoving, Idle);
+    machine.add_transition(Idle, Jump, Jumping);
+    machine.add_transition(Walking, Jump, Jumping);
+    machine.add_transition(Idle, Fall, Falling);
+    machine.add_transition(Walking, Fall, Falling);
+    // Wall jumps happen in the air, so they jump again from either airborne state
+    machine.add_transition(Jumping, Jump, Jumping);
+    machine.add_transition(Falling, Jump, Jumping);
+    machine.add_transition(Jumping, Land, Idle);
+    machine.add_transition(Falling, Land, Idle);
+    machine
+}
+
+const PLAYER_SIZE: f32 = 50.0;
+const MOVE_SPEED: f32 = 150.0;
+// How quickly horizontal speed changes towards what the player is steering for
+const ACCELERATION: f32 = 900.0;
+const GRAVITY: f32 = 500.0;
+const JUMP_VELOCITY: f32 = -300.0;
+// Sideways speed given by pushing off a wall
+const WALL_JUMP_PUSH: f32 = 250.0;
+// Letting go of jump while still rising keeps this much of the upward speed, so short taps give
+// short hops
+const JUMP_CUT: f32 = 0.4;
+const DASH_SPEED: f32 = 600.0;
+// How long a dash lasts, and how long after it starts until the next one is allowed
+const DASH_DURATION: f32 = 0.15;
+const DASH_COOLDOWN: f32 = 0.8;
+// How long one-way platforms are ignored after dropping through one
+const DROP_THROUGH_TIME: f32 = 0.25;
+// Players (re)spawn at this height and fall to the ground
+const SPAWN_Y: f32 = 400.0;
+// The level is wider than the screen; the camera follows the player across it
+const LEVEL_WIDTH: f32 = 1600.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WallSide {
+    Left,
+    Right,
+}
+
+/// True if the two rectangles share some area. Unlike `Rect::overlaps`, rectangles that only touch
+/// along an edge (like a player standing on the ground) don't count.
+fn overlapping(a: &Rect, b: &Rect) -> bool {
+    a.left() < b.right() && a.right() > b.left() && a.top() < b.bottom() && a.bottom() > b.top()
+}
+
+/// True if a player who was at `prev_y` last frame and is now at `player` lands on a one-way
+/// platform. Platforms only stop a player coming down onto them from above, so a player jumping
+/// up through one (or already below its top) passes straight through.
+fn one_way_collides(player: &Rect, prev_y: f32, platform: &Rect) -> bool {
+    let was_above = prev_y + player.h <= platform.y;
+    let reaches_top = player.bottom() >= platform.y;
+    let overlaps_horizontally = player.left() < platform.right() && player.right() > platform.left();
+    was_above && reaches_top && overlaps_horizontally
+}
+
+/// The checkpoint to respawn at: the furthest one at or behind `pos`, or the first one if `pos`
+/// is before all of them. `checkpoints` must not be empty.
+fn nearest_checkpoint(pos: f32, checkpoints: &[f32]) -> f32 {
+    checkpoints.iter()
+        .copied()
+        .filter(|&checkpoint| checkpoint <= pos)
+        .reduce(f32::max)
+        .unwrap_or_else(|| checkpoints.iter().copied().fold(f32::INFINITY, f32::min))
+}
+
+/// A place the player respawns from after dying, once they've reached it
+struct Checkpoint {
+    x: f32,
+}
+
+/// How far a background layer has scrolled for the given camera position. Factors below 1.0
+/// scroll slower than the camera, so the layer looks further away; 0.0 never moves.
+fn parallax_offset(camera_x: f32, factor: f32) -> f32 {
+    camera_x * factor
+}
+
+/// A row of evenly spaced background shapes scrolling at `factor` of the camera speed
+struct ParallaxLayer {
+    factor: f32,
+    spacing: f32,
+    width: f32,
+    top: f32,
+    color: Color,
+}
+
+impl ParallaxLayer {
+    fn draw(&self, camera_x: f32) {
+        // Start from the first shape that is at least partly on screen and repeat across it
+        let offset = parallax_offset(camera_x, self.factor).rem_euclid(self.spacing);
+        let mut x = -offset;
+        while x < screen_width() {
+            draw_rectangle(x, self.top, self.width, 500.0 - self.top, self.color);
+            x += self.spacing;
+        }
+    }
+}
+
+/// Everything the player can collide with
+struct Level {
+    /// Blocks movement from every side
+    solids: Vec<Rect>,
+    /// Can be jumped up through and only landed on from above
+    platforms: Vec<Rect>,
+    /// Kill the player on touch
+    hazards: Vec<Rect>,
+    /// The first checkpoint is where the player starts
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl Level {
+    fn checkpoint_positions(&self) -> Vec<f32> {
+        self.checkpoints.iter().map(|checkpoint| checkpoint.x).collect()
+    }
+}
+
+/// The velocity of a jump off a wall on the given side: upwards and away from the wall
+fn wall_jump_velocity(touching: WallSide) -> (f32, f32) {
+    match touching {
+        WallSide::Left => (WALL_JUMP_PUSH, JUMP_VELOCITY),
+        WallSide::Right => (-WALL_JUMP_PUSH, JUMP_VELOCITY),
+    }
+}
+
+/// The keys that control the player this frame
+struct PlayerInput {
+    left: bool,
+    right: bool,
+    down: bool,
+    jump_pressed: bool,
+    jump_released: bool,
+    dash_pressed: bool,
+}
+
+impl PlayerInput {
+    fn read() -> Self {
+        PlayerInput {
+            left: is_key_down(KeyCode::A) || is_key_down(KeyCode::Left),
+            right: is_key_down(KeyCode::D) || is_key_down(KeyCode::Right),
+            down: is_key_down(KeyCode::S) || is_key_down(KeyCode::Down),
+            jump_pressed: is_key_pressed(KeyCode::W) || is_key_pressed(KeyCode::Up),
+            jump_released: is_key_released(KeyCode::W) || is_key_released(KeyCode::Up),
+            dash_pressed: is_key_pressed(KeyCode::LeftShift) || is_key_pressed(KeyCode::RightShift),
+        }
+    }
+}
+
+struct Player {
+    x: f32,
+    y: f32,
+    vel_x: f32,
+    vel_y: f32,
+    on_ground: bool,
+    touching_wall: Option<WallSide>,
+    // 1.0 when facing right, -1.0 when facing left
+    facing: f32,
+    // Time left in the current dash, and until another dash is allowed
+    dash_timer: f32,
+    dash_cooldown: f32,
+    on_platform: bool,
+    // Time left during which one-way platforms are ignored
+    drop_timer: f32,
+    state: StateMachine<PlayerState, PlayerEvent>,
+}
+
+impl Player {
+    fn new(x: f32, y: f32) -> Self {
+        Player {
+            x,
+            y,
+            vel_x: 0.0,
+            vel_y: 0.0,
+            on_ground: false,
+            touching_wall: None,
+            facing: 1.0,
+            dash_timer: 0.0,
+            dash_cooldown: 0.0,
+            on_platform: false,
+            drop_timer: 0.0,
+            state: player_state_machine(),
+        }
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, PLAYER_SIZE, PLAYER_SIZE)
+    }
+
+    fn update(&mut self, input: &PlayerInput, dt: f32, level: &Level) {
+        let solids = &level.solids;
+        // Steer towards walking speed; anything else (like a wall jump's push) fades out
+        let target_vel_x = match (input.left, input.right) {
+            (true, false) => -MOVE_SPEED,
+            (false, true) => MOVE_SPEED,
+            _ => 0.0,
+        };
+        // A dash keeps going the way it started, so steering only turns the player between dashes
+        if target_vel_x != 0.0 && self.dash_timer == 0.0 {
+            self.facing = target_vel_x.signum();
+        }
+
+        self.dash_timer = (self.dash_timer - dt).max(0.0);
+        self.dash_cooldown = (self.dash_cooldown - dt).max(0.0);
+        if input.dash_pressed && self.dash_cooldown == 0.0 {
+            self.dash_timer = DASH_DURATION;
+            self.dash_cooldown = DASH_COOLDOWN;
+        }
+
+        if self.dash_timer > 0.0 {
+            // A dash holds its full speed until it runs out, whatever the player steers
+            self.vel_x = DASH_SPEED * self.facing;
+        } else {
+            let max_change = ACCELERATION * dt;
+            self.vel_x += (target_vel_x - self.vel_x).clamp(-max_change, max_change);
+        }
+
+        self.drop_timer = (self.drop_timer - dt).max(0.0);
+        if input.jump_pressed {
+            if self.on_platform && input.down {
+                // Down+Jump drops through the platform instead of jumping
+                self.drop_timer = DROP_THROUGH_TIME;
+            } else if self.on_ground {
+                self.vel_y = JUMP_VELOCITY;
+                self.state.handle(PlayerEvent::Jump);
+            } else if let Some(side) = self.touching_wall {
+                (self.vel_x, self.vel_y) = wall_jump_velocity(side);
+                self.state.handle(PlayerEvent::Jump);
+            }
+        }
+
+        if input.jump_released && self.vel_y < 0.0 {
+            self.vel_y *= JUMP_CUT;
+        }
+
+        // Apply gravity
+        self.vel_y += GRAVITY * dt;
+        let mut dy = self.vel_y * dt;
+        if input.down {
+            dy += MOVE_SPEED * dt;
+        }
+
+        // Move one axis at a time so a collision can be pushed back out along that axis
+        self.x += self.vel_x * dt;
+        for solid in solids {
+            if overlapping(&self.rect(), solid) {
+                self.x = if self.vel_x > 0.0 { solid.x - PLAYER_SIZE } else { solid.right() };
+                self.vel_x = 0.0;
+            }
+        }
+        let prev_y = self.y;
+        let was_on_ground = self.on_ground;
+        self.y += dy;
+        self.on_ground = false;
+        self.on_platform = false;
+        for solid in solids {
+            if overlapping(&self.rect(), solid) {
+                if dy > 0.0 {
+                    self.y = solid.y - PLAYER_SIZE;
+                    self.on_ground = true;
+                } else {
+                    self.y = solid.bottom();
+                }
+                self.vel_y = 0.0;
+            }
+        }
+        if self.drop_timer == 0.0 {
+            for platform in &level.platforms {
+                if one_way_collides(&self.rect(), prev_y, platform) {
+                    self.y = platform.y - PLAYER_SIZE;
+                    self.vel_y = 0.0;
+                    self.on_ground = true;
+                    self.on_platform = true;
+                }
+            }
+        }
+
+        // Walls only matter in the air, for wall jumps
+        let touches = |dx: f32| {
+            let probe = self.rect().offset(vec2(dx, 0.0));
+            solids.iter().any(|solid| overlapping(&probe, solid))
+        };
+        self.touching_wall = if self.on_ground {
+            None
+        } else if touches(-1.0) {
+            Some(WallSide::Left)
+        } else if touches(1.0) {
+            Some(WallSide::Right)
+        } else {
+            None
+        };
+
+        // Events that don't apply to the current state (like landing while walking) are ignored
+        if was_on_ground && !self.on_ground {
+            // Walking off a ledge; a jump has already moved the machine to Jumping, so this is ignored then
+            self.state.handle(PlayerEvent::Fall);
+        }
+        if self.on_ground {
+            self.state.handle(PlayerEvent::Land);
+            let moving = input.left || input.right;
+            self.state.handle(if moving { PlayerEvent::StartMoving } else { PlayerEvent::StopMoving });
+        }
+    }
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "Simple Platformer".to_owned(),
@@ -12,10 +348,46 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut player_x = 100.0;
-    let mut player_y = 400.0;
-    let mut player_vel_y = 0.0;
-    let mut on_ground = false;
+
+    // The ground has a pit in it; falling in counts as dying
+    let grounds = [Rect::new(0.0, 500.0, 950.0, 100.0), Rect::new(1050.0, 500.0, LEVEL_WIDTH - 1050.0, 100.0)];
+    // A pillar to wall jump off, plus invisible walls at the level edges to keep the player in it
+    let pillar = Rect::new(500.0, 250.0, 40.0, 250.0);
+    let level = Level {
+        solids: vec![
+            grounds[0],
+            grounds[1],
+            pillar,
+            Rect::new(-100.0, -1000.0, 100.0, 1600.0),
+            Rect::new(LEVEL_WIDTH, -1000.0, 100.0, 1600.0),
+        ],
+        platforms: vec![
+            Rect::new(150.0, 390.0, 150.0, 12.0),
+            Rect::new(280.0, 290.0, 150.0, 12.0),
+            Rect::new(1250.0, 380.0, 150.0, 12.0),
+        ],
+        hazards: vec![Rect::new(600.0, 485.0, 110.0, 15.0)],
+        checkpoints: vec![
+            Checkpoint { x: 100.0 },
+            Checkpoint { x: 320.0 },
+            Checkpoint { x: 720.0 },
+            Checkpoint { x: 1150.0 },
+        ],
+    };
+    // Furthest layer first so nearer ones are drawn over it
+    let background = [
+        ParallaxLayer { factor: 0.2, spacing: 420.0, width: 260.0, top: 300.0, color: Color::from_rgba(150, 180, 210, 255) },
+        ParallaxLayer { factor: 0.5, spacing: 300.0, width: 160.0, top: 380.0, color: Color::from_rgba(120, 160, 120, 255) },
+    ];
+    let checkpoint_positions = level.checkpoint_positions();
+    let mut respawn_x = checkpoint_positions[0];
+    let mut player = Player::new(respawn_x, SPAWN_Y);
+    // Dust kicked up on landing and debris left behind on death
+    let mut dust = ParticleSystem::new(0.4, GRAVITY, BEIGE);
+    let mut debris = ParticleSystem::new(0.8, GRAVITY, RED);
+    let mut shake = ScreenShake::default();
+    let shake_rng = macroquad::rand::RandGenerator::new();
+    shake_rng.srand(0x5EED);
 
     loop {
         // Clear screen with light blue background
@@ -24,48 +396,210 @@ async fn main() {
         // Get delta time
         let dt = get_frame_time();
 
-        // Handle input
-        if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
-            player_x -= 150.0 * dt;
-        }
-        if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {
-            player_x += 150.0 * dt;
-        }
-        if is_key_down(KeyCode::S) || is_key_down(KeyCode::Down) {
-            player_y += 150.0 * dt;
-        }
-        if (is_key_pressed(KeyCode::W) || is_key_pressed(KeyCode::Up)) && on_ground {
-            player_vel_y = -300.0;
+        // Handle input and move the player
+        let input = PlayerInput::read();
+        let was_on_ground = player.on_ground;
+        player.update(&input, dt, &level);
+        let feet = vec2(player.x + PLAYER_SIZE / 2.0, player.y + PLAYER_SIZE);
+        if player.on_ground && !was_on_ground {
+            dust.emit(feet, 8, (vec2(-80.0, -120.0), vec2(80.0, -40.0)));
         }
 
-        // Apply gravity
-        player_vel_y += 500.0 * dt;
-        player_y += player_vel_y * dt;
-
-        // Simple ground collision
-        if player_y > 500.0 {
-            player_y = 500.0;
-            player_vel_y = 0.0;
-            on_ground = true;
-        } else {
-            on_ground = false;
+        // Touching a checkpoint moves the respawn point up to it; dying sends the player back there
+        respawn_x = respawn_x.max(nearest_checkpoint(player.x, &checkpoint_positions));
+        let dead = player.y > screen_height() || level.hazards.iter().any(|hazard| overlapping(&player.rect(), hazard));
+        if dead {
+            let center = vec2(player.x + PLAYER_SIZE / 2.0, player.y + PLAYER_SIZE / 2.0);
+            debris.emit(center, 20, (vec2(-200.0, -300.0), vec2(200.0, -50.0)));
+            shake.start(12.0, 0.4);
+            player = Player::new(respawn_x, SPAWN_Y);
         }
+        dust.update(dt);
+        debris.update(dt);
 
-        // Keep player on screen
-        if player_x < 0.0 { player_x = 0.0; }
-        if player_x > 750.0 { player_x = 750.0; }
+        // Keep the player centred, without showing anything past the level edges
+        let (shake_x, shake_y) = shake.offset(dt, &shake_rng);
+        let camera_x = (player.x + PLAYER_SIZE / 2.0 - screen_width() / 2.0)
+            .clamp(0.0, (LEVEL_WIDTH - screen_width()).max(0.0))
+            + shake_x as f32;
+        let camera_y = shake_y as f32;
+        for layer in &background {
+            layer.draw(camera_x);
+        }
 
-        // Draw ground
-        draw_rectangle(0.0, 500.0, 800.0, 100.0, GREEN);
+        // Draw the level, shifted by the camera
+        for ground in &grounds {
+            draw_rectangle(ground.x - camera_x, ground.y - camera_y, ground.w, ground.h, GREEN);
+        }
+        draw_rectangle(pillar.x - camera_x, pillar.y - camera_y, pillar.w, pillar.h, DARKGREEN);
+        for platform in &level.platforms {
+            draw_rectangle(platform.x - camera_x, platform.y - camera_y, platform.w, platform.h, BROWN);
+        }
+        for hazard in &level.hazards {
+            draw_rectangle(hazard.x - camera_x, hazard.y - camera_y, hazard.w, hazard.h, MAROON);
+        }
+        for checkpoint in &level.checkpoints {
+            let color = if checkpoint.x <= respawn_x { GOLD } else { GRAY };
+            let x = checkpoint.x - camera_x;
+            draw_line(x, 500.0 - camera_y, x, 440.0 - camera_y, 3.0, DARKGRAY);
+            draw_rectangle(x, 440.0 - camera_y, 20.0, 14.0, color);
+        }
 
         // Draw player
-        draw_rectangle(player_x, player_y, 50.0, 50.0, RED);
+        draw_rectangle(player.x - camera_x, player.y - camera_y, PLAYER_SIZE, PLAYER_SIZE, RED);
+        dust.draw(camera_x, camera_y);
+        debris.draw(camera_x, camera_y);
 
         // Draw simple instructions
-        draw_text("WASD or Arrow Keys to move", 10.0, 30.0, 24.0, BLACK);
-        draw_text("W or Up to jump", 10.0, 60.0, 24.0, BLACK);
+        draw_text("WASD or Arrow Keys to move, Shift to dash", 10.0, 30.0, 24.0, BLACK);
+        draw_text("W or Up to jump (hold for higher), also off walls in the air", 10.0, 60.0, 24.0, BLACK);
+        draw_text("Down + Jump to drop through platforms", 10.0, 90.0, 24.0, BLACK);
+        draw_text(&format!("State: {:?}", player.state.current()), 10.0, 120.0, 24.0, BLACK);
 
         // Update frame
         next_frame().await;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_input() -> PlayerInput {
+        PlayerInput { left: false, right: false, down: false, jump_pressed: false, jump_released: false, dash_pressed: false }
+    }
+
+    /// A floor along y = 500 and nothing else
+    fn floor_level() -> Level {
+        Level {
+            solids: vec![Rect::new(-1000.0, 500.0, 3000.0, 50.0)],
+            platforms: Vec::new(),
+            hazards: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// A player standing on the floor of `floor_level`
+    fn grounded_player(level: &Level) -> Player {
+        let mut player = Player::new(0.0, 500.0 - PLAYER_SIZE);
+        player.update(&no_input(), 1.0 / 60.0, level);
+        assert!(player.on_ground);
+        player
+    }
+
+    #[test]
+    fn walking_off_a_ledge_falls_and_can_wall_jump() {
+        let mut machine = player_state_machine();
+        machine.handle(PlayerEvent::StartMoving);
+        assert_eq!(machine.handle(PlayerEvent::Fall), Some(&PlayerState::Falling));
+        assert_eq!(machine.handle(PlayerEvent::Jump), Some(&PlayerState::Jumping));
+        // A second wall jump keeps the player jumping
+        assert_eq!(machine.handle(PlayerEvent::Jump), Some(&PlayerState::Jumping));
+        assert_eq!(machine.handle(PlayerEvent::Land), Some(&PlayerState::Idle));
+    }
+
+    #[test]
+    fn falling_after_a_jump_is_ignored() {
+        let mut machine = player_state_machine();
+        machine.handle(PlayerEvent::Jump);
+        assert_eq!(machine.handle(PlayerEvent::Fall), None);
+        assert_eq!(machine.current(), &PlayerState::Jumping);
+    }
+
+    #[test]
+    fn wall_jumps_push_away_from_the_wall() {
+        let (left_x, left_y) = wall_jump_velocity(WallSide::Left);
+        let (right_x, right_y) = wall_jump_velocity(WallSide::Right);
+        assert!(left_x > 0.0);
+        assert_eq!(right_x, -left_x);
+        assert_eq!((left_y, right_y), (JUMP_VELOCITY, JUMP_VELOCITY));
+    }
+
+    /// The highest point (lowest y) reached by a jump whose key is let go after `hold_frames`
+    fn jump_apex(hold_frames: usize) -> f32 {
+        let level = floor_level();
+        let mut player = grounded_player(&level);
+        let dt = 1.0 / 60.0;
+        player.update(&PlayerInput { jump_pressed: true, ..no_input() }, dt, &level);
+        let mut apex = player.y;
+        for frame in 1..120 {
+            player.update(&PlayerInput { jump_released: frame == hold_frames, ..no_input() }, dt, &level);
+            apex = apex.min(player.y);
+        }
+        apex
+    }
+
+    #[test]
+    fn releasing_jump_early_gives_a_lower_apex() {
+        let held = jump_apex(usize::MAX);
+        let tapped = jump_apex(3);
+        assert!(tapped > held, "tapped apex {} should be below held apex {}", tapped, held);
+        // Letting go after the player starts falling changes nothing
+        assert_eq!(jump_apex(100), held);
+    }
+
+    #[test]
+    fn dash_bursts_expires_and_waits_for_its_cooldown() {
+        let level = floor_level();
+        let mut player = grounded_player(&level);
+        let dt = 0.05;
+        let dash = PlayerInput { dash_pressed: true, ..no_input() };
+
+        player.update(&dash, dt, &level);
+        assert_eq!(player.vel_x, DASH_SPEED);
+        // Steering the other way doesn't slow a dash down
+        player.update(&PlayerInput { left: true, ..no_input() }, dt, &level);
+        assert_eq!(player.vel_x, DASH_SPEED);
+
+        // Past the dash's duration, normal acceleration takes over again
+        for _ in 0..3 {
+            player.update(&no_input(), dt, &level);
+        }
+        assert!(player.vel_x < DASH_SPEED);
+
+        // Still cooling down
+        player.update(&dash, dt, &level);
+        assert_eq!(player.dash_timer, 0.0);
+        assert!(player.vel_x < DASH_SPEED);
+
+        for _ in 0..20 {
+            player.update(&no_input(), dt, &level);
+        }
+        player.update(&PlayerInput { dash_pressed: true, left: true, ..no_input() }, dt, &level);
+        assert_eq!(player.vel_x, -DASH_SPEED);
+    }
+
+    #[test]
+    fn one_way_platforms_only_catch_a_falling_player() {
+        let platform = Rect::new(0.0, 300.0, 200.0, 20.0);
+        // Rising from below into the platform
+        let rising = Rect::new(50.0, 290.0, PLAYER_SIZE, PLAYER_SIZE);
+        assert!(!one_way_collides(&rising, 310.0, &platform));
+        // Falling from just above onto it
+        let falling = Rect::new(50.0, 255.0, PLAYER_SIZE, PLAYER_SIZE);
+        assert!(one_way_collides(&falling, 245.0, &platform));
+        // Falling past its side
+        let beside = Rect::new(250.0, 255.0, PLAYER_SIZE, PLAYER_SIZE);
+        assert!(!one_way_collides(&beside, 245.0, &platform));
+    }
+
+    #[test]
+    fn respawn_uses_the_latest_checkpoint_crossed() {
+        let checkpoints = [100.0, 600.0, 1200.0];
+        let mut respawn_x = nearest_checkpoint(0.0, &checkpoints);
+        assert_eq!(respawn_x, 100.0);
+        // Walk past the second and third checkpoints, then back again
+        for x in [300.0, 700.0, 1300.0, 800.0] {
+            respawn_x = respawn_x.max(nearest_checkpoint(x, &checkpoints));
+        }
+        assert_eq!(respawn_x, 1200.0);
+        assert_eq!(nearest_checkpoint(650.0, &checkpoints), 600.0);
+    }
+
+    #[test]
+    fn parallax_layers_scroll_at_a_fraction_of_the_camera() {
+        assert_eq!(parallax_offset(400.0, 0.0), 0.0);
+        assert_eq!(parallax_offset(400.0, 0.5), 200.0);
+        assert_eq!(parallax_offset(400.0, 1.0), 400.0);
+    }
+}
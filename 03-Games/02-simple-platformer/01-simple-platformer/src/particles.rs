@@ -0,0 +1,103 @@
+use macroquad::prelude::*;
+
+/// A single short-lived particle, in world coordinates
+pub struct Particle {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Move the particle by its velocity and pull it down by `gravity`.
+    /// Returns false once the particle has outlived its lifetime.
+    pub fn update(&mut self, dt: f32, gravity: f32) -> bool {
+        self.vel.y += gravity * dt;
+        self.pos += self.vel * dt;
+        self.age += dt;
+        self.age < self.lifetime
+    }
+}
+
+/// A pool of particles that all share a lifetime, gravity and color
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    lifetime: f32,
+    gravity: f32,
+    color: Color,
+}
+
+impl ParticleSystem {
+    pub fn new(lifetime: f32, gravity: f32, color: Color) -> Self {
+        Self {
+            particles: Vec::new(),
+            lifetime,
+            gravity,
+            color,
+        }
+    }
+
+    /// Spawn `count` particles at `pos`, each with a random velocity between the two corners of `vel_range`
+    pub fn emit(&mut self, pos: Vec2, count: usize, vel_range: (Vec2, Vec2)) {
+        let (min, max) = vel_range;
+        for _ in 0..count {
+            self.particles.push(Particle {
+                pos,
+                vel: vec2(rand::gen_range(min.x, max.x), rand::gen_range(min.y, max.y)),
+                age: 0.0,
+                lifetime: self.lifetime,
+            });
+        }
+    }
+
+    /// Advance every particle and drop the ones that have expired
+    pub fn update(&mut self, dt: f32) {
+        let gravity = self.gravity;
+        self.particles.retain_mut(|particle| particle.update(dt, gravity));
+    }
+
+    /// Draw the particles shifted by the camera, fading out as they age
+    pub fn draw(&self, camera_x: f32, camera_y: f32) {
+        for particle in &self.particles {
+            let alpha = 1.0 - particle.age / particle.lifetime;
+            let color = Color::new(self.color.r, self.color.g, self.color.b, self.color.a * alpha);
+            draw_rectangle(particle.pos.x - camera_x, particle.pos.y - camera_y, 4.0, 4.0, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(vel: Vec2) -> Particle {
+        Particle { pos: vec2(10.0, 20.0), vel, age: 0.0, lifetime: 1.0 }
+    }
+
+    #[test]
+    fn particles_move_by_their_velocity() {
+        let mut particle = particle(vec2(30.0, -40.0));
+        assert!(particle.update(0.5, 0.0));
+        assert_eq!(particle.pos, vec2(25.0, 0.0));
+        assert_eq!(particle.age, 0.5);
+    }
+
+    #[test]
+    fn gravity_speeds_up_the_fall() {
+        let mut particle = particle(Vec2::ZERO);
+        particle.update(0.5, 100.0);
+        assert_eq!(particle.vel, vec2(0.0, 50.0));
+        assert_eq!(particle.pos, vec2(10.0, 45.0));
+    }
+
+    #[test]
+    fn particles_expire_after_their_lifetime() {
+        let mut system = ParticleSystem::new(1.0, 0.0, WHITE);
+        system.emit(Vec2::ZERO, 5, (vec2(-10.0, -10.0), vec2(10.0, 10.0)));
+        assert_eq!(system.particles.len(), 5);
+        system.update(0.6);
+        assert_eq!(system.particles.len(), 5);
+        system.update(0.6);
+        assert!(system.particles.is_empty());
+    }
+}
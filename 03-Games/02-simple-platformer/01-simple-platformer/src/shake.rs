@@ -0,0 +1,74 @@
+use macroquad::rand::RandGenerator;
+
+/// Shakes the camera by up to `intensity` pixels, fading out linearly over `duration` seconds
+#[derive(Default)]
+pub struct ScreenShake {
+    pub intensity: f32,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+impl ScreenShake {
+    /// Start a new shake, replacing any that is still running
+    pub fn start(&mut self, intensity: f32, duration: f32) {
+        self.intensity = intensity;
+        self.duration = duration;
+        self.elapsed = 0.0;
+    }
+
+    /// Advance the shake by `dt` and return a random camera offset, which is (0, 0) once it's over
+    pub fn offset(&mut self, dt: f32, rng: &RandGenerator) -> (i32, i32) {
+        if self.duration <= 0.0 {
+            return (0, 0);
+        }
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        let strength = self.intensity * (1.0 - self.elapsed / self.duration);
+        let dx = rng.gen_range(-strength, strength).round() as i32;
+        let dy = rng.gen_range(-strength, strength).round() as i32;
+        (dx, dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(seed: u64) -> RandGenerator {
+        let rng = RandGenerator::new();
+        rng.srand(seed);
+        rng
+    }
+
+    #[test]
+    fn offset_decays_to_zero_by_the_end() {
+        let mut shake = ScreenShake::default();
+        shake.start(20.0, 1.0);
+        let rng = seeded(3);
+        let mut limit = 20.0;
+        for _ in 0..10 {
+            let (dx, dy) = shake.offset(0.1, &rng);
+            // Each step can only be as strong as what's left of the shake
+            limit -= 2.0;
+            assert!(dx.abs() as f32 <= limit + 0.5 && dy.abs() as f32 <= limit + 0.5, "({}, {}) over {}", dx, dy, limit);
+        }
+        assert_eq!(shake.offset(0.1, &rng), (0, 0));
+    }
+
+    #[test]
+    fn starting_again_restarts_the_fade() {
+        let rng = seeded(7);
+        let mut shake = ScreenShake::default();
+        assert_eq!(shake.offset(0.1, &rng), (0, 0));
+
+        shake.start(10.0, 1.0);
+        shake.offset(0.9, &rng);
+        shake.start(10.0, 1.0);
+        assert_eq!(shake.elapsed, 0.0);
+        // Back at close to full strength, the camera actually moves
+        let moved = (0..20).any(|_| {
+            shake.elapsed = 0.0;
+            shake.offset(0.0, &rng) != (0, 0)
+        });
+        assert!(moved);
+    }
+}
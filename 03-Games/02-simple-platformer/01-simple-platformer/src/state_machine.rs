@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A finite state machine that moves between states of type `S` in response to events of type `E`.
+/// Events that have no transition from the current state are ignored.
+pub struct StateMachine<S, E> {
+    current: S,
+    transitions: HashMap<(S, E), S>,
+}
+
+impl<S: Clone + Eq + Hash, E: Eq + Hash> StateMachine<S, E> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            transitions: HashMap::new(),
+        }
+    }
+
+    /// Make `event` move the machine from `from` to `to`, replacing any existing transition
+    pub fn add_transition(&mut self, from: S, event: E, to: S) {
+        self.transitions.insert((from, event), to);
+    }
+
+    /// Apply an event, returning the new state, or None if the event isn't valid in the current state
+    pub fn handle(&mut self, event: E) -> Option<&S> {
+        let next = self.transitions.get(&(self.current.clone(), event))?;
+        self.current = next.clone();
+        Some(&self.current)
+    }
+
+    pub fn current(&self) -> &S {
+        &self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum State {
+        Idle,
+        Walk,
+        Jump,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Event {
+        Move,
+        Stop,
+        Jump,
+        Land,
+    }
+
+    fn machine() -> StateMachine<State, Event> {
+        let mut machine = StateMachine::new(State::Idle);
+        machine.add_transition(State::Idle, Event::Move, State::Walk);
+        machine.add_transition(State::Walk, Event::Stop, State::Idle);
+        machine.add_transition(State::Walk, Event::Jump, State::Jump);
+        machine.add_transition(State::Jump, Event::Land, State::Idle);
+        machine
+    }
+
+    #[test]
+    fn idle_walks_then_jumps() {
+        let mut machine = machine();
+        assert_eq!(machine.current(), &State::Idle);
+        assert_eq!(machine.handle(Event::Move), Some(&State::Walk));
+        assert_eq!(machine.handle(Event::Jump), Some(&State::Jump));
+        assert_eq!(machine.current(), &State::Jump);
+        assert_eq!(machine.handle(Event::Land), Some(&State::Idle));
+    }
+
+    #[test]
+    fn invalid_transitions_are_rejected() {
+        let mut machine = machine();
+        assert_eq!(machine.handle(Event::Jump), None);
+        assert_eq!(machine.handle(Event::Land), None);
+        assert_eq!(machine.current(), &State::Idle);
+
+        machine.handle(Event::Move);
+        machine.handle(Event::Jump);
+        assert_eq!(machine.handle(Event::Move), None);
+        assert_eq!(machine.current(), &State::Jump);
+    }
+
+    #[test]
+    fn later_transitions_replace_earlier_ones() {
+        let mut machine = machine();
+        machine.add_transition(State::Idle, Event::Move, State::Jump);
+        assert_eq!(machine.handle(Event::Move), Some(&State::Jump));
+    }
+}
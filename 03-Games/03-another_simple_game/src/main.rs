@@ -5,7 +5,8 @@ use sdl2::render::{WindowCanvas, Texture};
 use sdl2::rect::{Point, Rect};
 use sdl2::image::{self, LoadTexture, InitFlag};
 use std::time::Duration;
-use std::collections::VecDeque;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
 
 const PLAYER_MOVEMENT_SPEED: i32 = 20;
 const FRAME_RATE: u32 = 20; // More standard frame rate is 60 FPS
@@ -40,6 +41,47 @@ impl Direction {
     const fn is_vertical(self) -> bool {
         matches!(self, Direction::Up | Direction::Down)
     }
+
+    /// Returns the direction pointing the other way
+    const fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// Returns the direction after a quarter turn counter-clockwise
+    const fn turn_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    /// Returns the direction after a quarter turn clockwise
+    const fn turn_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// Returns the direction for an arrow key
+    fn from_keycode(k: Keycode) -> Option<Direction> {
+        match k {
+            Keycode::Up => Some(Direction::Up),
+            Keycode::Down => Some(Direction::Down),
+            Keycode::Left => Some(Direction::Left),
+            Keycode::Right => Some(Direction::Right),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -159,27 +201,77 @@ impl InputHandler {
     }
 }
 
+/// Holds at most one value of each type, like the resources of an ECS world but without needing
+/// one
+#[derive(Default)]
+struct ResourceStore {
+    resources: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl ResourceStore {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a resource, replacing any existing resource of the same type
+    fn insert<T: Any>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    fn get<T: Any>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+}
+
+/// Counts game updates, used to cycle the background color
+struct FrameCounter(u8);
+
 /// Game state management
 struct Game {
     player: Player,
     input_handler: InputHandler,
+    resources: ResourceStore,
 }
 
 impl Game {
     fn new() -> Self {
+        let mut resources = ResourceStore::new();
+        resources.insert(FrameCounter(0));
+
         Self {
             player: Player::new(Point::new(0, 0)),
             input_handler: InputHandler::new(),
+            resources,
         }
     }
 
+    /// The current frame number, wrapping around after 255
+    fn frame(&self) -> u8 {
+        self.resources.get::<FrameCounter>().map_or(0, |counter| counter.0)
+    }
+
     fn handle_key_event(&mut self, keycode: Keycode, is_pressed: bool) {
-        let direction = match keycode {
-            Keycode::Left => Direction::Left,
-            Keycode::Right => Direction::Right,
-            Keycode::Up => Direction::Up,
-            Keycode::Down => Direction::Down,
-            _ => return, // Ignore other keys
+        // Q/E turn the player a quarter turn and X turns them around, without moving
+        let turn: Option<fn(Direction) -> Direction> = match keycode {
+            Keycode::Q => Some(Direction::turn_left),
+            Keycode::E => Some(Direction::turn_right),
+            Keycode::X => Some(Direction::opposite),
+            _ => None,
+        };
+        if let Some(turn) = turn {
+            if is_pressed {
+                self.player.direction = turn(self.player.direction);
+            }
+            return;
+        }
+
+        let direction = match Direction::from_keycode(keycode) {
+            Some(direction) => direction,
+            None => return, // Ignore other keys
         };
 
         if is_pressed {
@@ -193,6 +285,10 @@ impl Game {
         let effective_direction = self.input_handler.get_effective_direction();
         self.player.set_movement(effective_direction);
         self.player.update();
+
+        if let Some(counter) = self.resources.get_mut::<FrameCounter>() {
+            counter.0 = counter.0.wrapping_add(1);
+        }
     }
 }
 
@@ -250,7 +346,6 @@ fn main() -> Result<(), String> {
     // Initialize game state
     let mut game = Game::new();
     let mut event_pump = sdl_context.event_pump()?;
-    let mut frame_counter = 0u8;
 
     // Game loop
     'running: loop {
@@ -275,8 +370,8 @@ fn main() -> Result<(), String> {
         game.update();
 
         // Render
-        frame_counter = frame_counter.wrapping_add(1);
-        let background_color = Color::RGB(frame_counter, 64, 255 - frame_counter);
+        let frame = game.frame();
+        let background_color = Color::RGB(frame, 64, 255 - frame);
         render(&mut canvas, background_color, &texture, &game.player)?;
 
         // Frame rate control
@@ -285,3 +380,64 @@ fn main() -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Volume(f32);
+
+    #[test]
+    fn resources_of_different_types_are_kept_apart() {
+        let mut store = ResourceStore::new();
+        store.insert(Volume(0.5));
+        store.insert(String::from("level-1"));
+
+        assert_eq!(store.get::<Volume>(), Some(&Volume(0.5)));
+        assert_eq!(store.get::<String>().map(String::as_str), Some("level-1"));
+        assert_eq!(store.get::<u32>(), None);
+
+        store.get_mut::<Volume>().unwrap().0 = 0.8;
+        store.insert(String::from("level-2"));
+        assert_eq!(store.get::<Volume>(), Some(&Volume(0.8)));
+        assert_eq!(store.get::<String>().map(String::as_str), Some("level-2"));
+    }
+
+    #[test]
+    fn frame_counter_advances_each_update_and_wraps() {
+        let mut game = Game::new();
+        assert_eq!(game.frame(), 0);
+        game.update();
+        game.update();
+        assert_eq!(game.frame(), 2);
+        for _ in 0..254 {
+            game.update();
+        }
+        assert_eq!(game.frame(), 0);
+    }
+
+    const ALL_DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+    #[test]
+    fn opposite_and_turns_cover_every_direction() {
+        use Direction::*;
+        assert_eq!(ALL_DIRECTIONS.map(Direction::opposite), [Down, Left, Up, Right]);
+        // Clockwise order, so turning right moves one step along it and turning left one step back
+        assert_eq!(ALL_DIRECTIONS.map(Direction::turn_right), [Right, Down, Left, Up]);
+        assert_eq!(ALL_DIRECTIONS.map(Direction::turn_left), [Left, Up, Right, Down]);
+        for direction in ALL_DIRECTIONS {
+            assert_eq!(direction.turn_left().turn_right(), direction);
+            assert_eq!(direction.turn_right().turn_right(), direction.opposite());
+        }
+    }
+
+    #[test]
+    fn only_arrow_keys_are_directions() {
+        assert_eq!(Direction::from_keycode(Keycode::Up), Some(Direction::Up));
+        assert_eq!(Direction::from_keycode(Keycode::Down), Some(Direction::Down));
+        assert_eq!(Direction::from_keycode(Keycode::Left), Some(Direction::Left));
+        assert_eq!(Direction::from_keycode(Keycode::Right), Some(Direction::Right));
+        assert_eq!(Direction::from_keycode(Keycode::W), None);
+    }
+}
@@ -4,6 +4,25 @@ use crate::components::*;
 
 pub struct Animator;
 
+/// Picks the direction an entity should face for the given velocity. The axis with the larger
+/// magnitude wins, and ties (including diagonals) favor the horizontal axis.
+pub fn facing_from_velocity(dx: f32, dy: f32) -> Direction {
+    if dx.abs() >= dy.abs() {
+        if dx < 0.0 { Direction::Left } else { Direction::Right }
+    } else if dy < 0.0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
+/// The direction a velocity's entity faces, whether or not it is currently moving, so an entity
+/// keeps facing the way it walked once it stops
+pub fn facing(velocity: &Velocity) -> Direction {
+    let (dx, dy) = velocity.offset();
+    facing_from_velocity(dx as f32, dy as f32)
+}
+
 impl<'a> System<'a> for Animator {
     type SystemData = (
         WriteStorage<'a, MovementAnimation>,
@@ -14,11 +33,15 @@ impl<'a> System<'a> for Animator {
     fn run(&mut self, (mut animations, mut sprites, velocities): Self::SystemData) {
         use self::Direction::*;
         for (animation, sprite, velocity) in (&mut animations, &mut sprites, &velocities).join() {
+            let facing = facing(velocity);
             if velocity.speed == 0 {
+                // Reset to the standing pose, still facing the last direction of movement
+                animation.current_frame = 0;
+                *sprite = animation.idle_frame(facing).clone();
                 continue;
             }
 
-            let frames = match velocity.direction {
+            let frames = match facing {
                 Left => &animation.left_frames,
                 Right => &animation.right_frames,
                 Up => &animation.up_frames,
@@ -30,3 +53,89 @@ impl<'a> System<'a> for Animator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::rect::Rect;
+
+    /// Walking frames are 10px apart along each row, with one row per direction
+    fn test_animation() -> MovementAnimation {
+        let row = |y| (0..3).map(|i| Sprite {spritesheet: 0, region: Rect::new(i * 10, y, 10, 10)}).collect::<Vec<_>>();
+        let (up_frames, down_frames, left_frames, right_frames) = (row(30), row(0), row(10), row(20));
+        MovementAnimation {
+            current_frame: 0,
+            up_idle_frame: up_frames[0].clone(),
+            down_idle_frame: down_frames[0].clone(),
+            left_idle_frame: left_frames[0].clone(),
+            right_idle_frame: right_frames[0].clone(),
+            up_frames,
+            down_frames,
+            left_frames,
+            right_frames,
+        }
+    }
+
+    fn world_with_mover(velocity: Velocity) -> (World, Entity) {
+        let mut world = World::new();
+        world.register::<MovementAnimation>();
+        world.register::<Sprite>();
+        world.register::<Velocity>();
+        let animation = test_animation();
+        let entity = world.create_entity()
+            .with(animation.idle_frame(Direction::Down).clone())
+            .with(animation)
+            .with(velocity)
+            .build();
+        (world, entity)
+    }
+
+    fn sprite_region(world: &World, entity: Entity) -> Rect {
+        world.read_storage::<Sprite>().get(entity).expect("entity has a sprite").region
+    }
+
+    fn stop(world: &mut World, entity: Entity) {
+        world.write_storage::<Velocity>().get_mut(entity).expect("entity has a velocity").speed = 0;
+    }
+
+    #[test]
+    fn stopping_after_moving_right_shows_the_right_idle_frame() {
+        let (mut world, entity) = world_with_mover(Velocity {speed: 20, direction: Direction::Right, secondary_direction: None});
+        Animator.run_now(&world.res);
+        Animator.run_now(&world.res);
+        assert_eq!(sprite_region(&world, entity), Rect::new(20, 20, 10, 10));
+
+        stop(&mut world, entity);
+        Animator.run_now(&world.res);
+        assert_eq!(sprite_region(&world, entity), Rect::new(0, 20, 10, 10));
+        assert_eq!(world.read_storage::<MovementAnimation>().get(entity).unwrap().current_frame, 0);
+    }
+
+    #[test]
+    fn facing_follows_a_single_axis() {
+        assert_eq!(facing_from_velocity(3.0, 0.0), Direction::Right);
+        assert_eq!(facing_from_velocity(-3.0, 0.0), Direction::Left);
+        assert_eq!(facing_from_velocity(0.0, -3.0), Direction::Up);
+        assert_eq!(facing_from_velocity(0.0, 3.0), Direction::Down);
+    }
+
+    #[test]
+    fn facing_picks_the_dominant_axis_and_ties_go_horizontal() {
+        assert_eq!(facing_from_velocity(1.0, -2.0), Direction::Up);
+        assert_eq!(facing_from_velocity(-2.0, 1.0), Direction::Left);
+        assert_eq!(facing_from_velocity(1.0, -1.0), Direction::Right);
+        assert_eq!(facing_from_velocity(-1.0, 1.0), Direction::Left);
+    }
+
+    #[test]
+    fn stopping_after_a_diagonal_keeps_the_walking_facing() {
+        let velocity = Velocity {speed: 20, direction: Direction::Up, secondary_direction: Some(Direction::Right)};
+        let (mut world, entity) = world_with_mover(velocity);
+        Animator.run_now(&world.res);
+        assert_eq!(sprite_region(&world, entity), Rect::new(10, 20, 10, 10));
+
+        stop(&mut world, entity);
+        Animator.run_now(&world.res);
+        assert_eq!(sprite_region(&world, entity), Rect::new(0, 20, 10, 10));
+    }
+}
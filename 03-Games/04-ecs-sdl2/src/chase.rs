@@ -0,0 +1,54 @@
+use sdl2::rect::Point;
+use specs::prelude::*;
+
+use crate::components::*;
+use crate::level::Level;
+
+/// How far an enemy moves in one physics step while chasing
+const CHASE_SPEED: i32 = 4;
+
+/// Moves alert enemies towards the player, finding a way around walls and mud
+pub struct Chase;
+
+/// Move `from` up to `max_distance` towards `to`, along the axis with the bigger gap first
+fn step_towards(from: Point, to: Point, max_distance: i32) -> Point {
+    let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+    if dx.abs() >= dy.abs() {
+        from.offset(dx.clamp(-max_distance, max_distance), 0)
+    } else {
+        from.offset(0, dy.clamp(-max_distance, max_distance))
+    }
+}
+
+impl<'a> System<'a> for Chase {
+    type SystemData = (
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Alert>,
+        ReadStorage<'a, Tween>,
+        ReadStorage<'a, Player>,
+        ReadExpect<'a, Level>,
+    );
+
+    fn run(&mut self, (mut positions, alerts, tweens, players, level): Self::SystemData) {
+        let player_position = match (&positions, &players).join().map(|(pos, _)| pos.0).next() {
+            Some(player_position) => player_position,
+            None => return,
+        };
+
+        // Enemies still making their entrance aren't ready to chase yet
+        for (position, _, _) in (&mut positions, &alerts, !&tweens).join() {
+            let path = match level.find_path(position.0, player_position) {
+                Some(path) => path,
+                None => continue,
+            };
+            // Head for the next tile on the path, or straight for the player once in the same tile
+            let target = match path.get(1) {
+                Some(&(x, y)) => level.cell_center(x, y),
+                None => player_position,
+            };
+            // Harder ground slows the enemy down
+            let cost = level.tile_at(position.0).move_cost().unwrap_or(1) as i32;
+            position.0 = step_towards(position.0, target, CHASE_SPEED / cost);
+        }
+    }
+}
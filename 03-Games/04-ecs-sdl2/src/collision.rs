@@ -0,0 +1,155 @@
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::{Point, Rect};
+use sdl2::surface::Surface;
+use std::collections::HashMap;
+
+use crate::components::*;
+
+/// Pixels with at least this much alpha count as solid
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Which pixels of a sprite are opaque, used for collisions that need to be more exact than the
+/// sprite's bounding box
+#[derive(Debug, Clone)]
+pub struct Mask {
+    width: u32,
+    height: u32,
+    opaque: Vec<bool>,
+}
+
+impl Mask {
+    /// A mask where every pixel is opaque
+    pub fn solid(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            opaque: vec![true; (width * height) as usize],
+        }
+    }
+
+    /// Builds a mask from the alpha channel of the given region of a spritesheet. The region must
+    /// lie entirely inside the surface.
+    pub fn from_surface(surface: &Surface, region: Rect) -> Result<Self, String> {
+        if !region_fits(region, surface.width(), surface.height()) {
+            return Err(format!("mask region {:?} is outside the {}x{} surface",
+                region, surface.width(), surface.height()));
+        }
+
+        let surface = surface.convert_format(PixelFormatEnum::RGBA32)?;
+        let pitch = surface.pitch() as usize;
+
+        let mut opaque = Vec::with_capacity((region.width() * region.height()) as usize);
+        surface.with_lock(|pixels| {
+            for y in region.y()..region.bottom() {
+                for x in region.x()..region.right() {
+                    // RGBA32 always stores the alpha as the last of the pixel's 4 bytes
+                    let alpha = pixels[y as usize * pitch + x as usize * 4 + 3];
+                    opaque.push(alpha >= ALPHA_THRESHOLD);
+                }
+            }
+        });
+
+        Ok(Self {
+            width: region.width(),
+            height: region.height(),
+            opaque,
+        })
+    }
+
+    /// Whether the pixel at (x, y), relative to the top-left of the mask, is opaque. Anything
+    /// outside the mask is transparent.
+    pub fn is_opaque(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        self.opaque[(y as u32 * self.width + x as u32) as usize]
+    }
+}
+
+/// Whether `region` lies entirely inside an image of the given size
+fn region_fits(region: Rect, width: u32, height: u32) -> bool {
+    region.x() >= 0 && region.y() >= 0
+        && region.right() <= width as i32
+        && region.bottom() <= height as i32
+}
+
+/// Checks whether two masks placed with their top-left corners at the given positions have any
+/// opaque pixels in common
+pub fn pixel_collides(a: &Mask, a_pos: Point, b: &Mask, b_pos: Point) -> bool {
+    let a_rect = Rect::new(a_pos.x(), a_pos.y(), a.width, a.height);
+    let b_rect = Rect::new(b_pos.x(), b_pos.y(), b.width, b.height);
+    let overlap = match a_rect.intersection(b_rect) {
+        Some(overlap) => overlap,
+        None => return false,
+    };
+
+    for y in overlap.y()..overlap.bottom() {
+        for x in overlap.x()..overlap.right() {
+            if a.is_opaque(x - a_pos.x(), y - a_pos.y()) && b.is_opaque(x - b_pos.x(), y - b_pos.y()) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Optional alpha masks for individual sprites. Sprites without a mask collide using their
+/// bounding box.
+#[derive(Debug, Default)]
+pub struct SpriteMasks(HashMap<(usize, Rect), Mask>);
+
+impl SpriteMasks {
+    pub fn insert(&mut self, sprite: &Sprite, mask: Mask) {
+        self.0.insert((sprite.spritesheet, sprite.region), mask);
+    }
+
+    pub fn get(&self, sprite: &Sprite) -> Option<&Mask> {
+        self.0.get(&(sprite.spritesheet, sprite.region))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a mask from rows of '#' (opaque) and '.' (transparent)
+    fn mask(rows: &[&str]) -> Mask {
+        Mask {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            opaque: rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect(),
+        }
+    }
+
+    #[test]
+    fn overlapping_transparent_corners_do_not_collide() {
+        let a = mask(&["##.", "#..", "..."]);
+        let b = mask(&["...", "..#", ".##"]);
+        // The boxes overlap completely, but the opaque pixels never meet
+        assert!(!pixel_collides(&a, Point::new(0, 0), &b, Point::new(0, 0)));
+        assert!(!pixel_collides(&a, Point::new(0, 0), &b, Point::new(1, 1)));
+    }
+
+    #[test]
+    fn overlapping_opaque_pixels_collide() {
+        let a = mask(&["##.", "#..", "..."]);
+        let b = mask(&["...", "..#", ".##"]);
+        // Shifting b up and left by two puts its corner pixel on a's
+        assert!(pixel_collides(&a, Point::new(0, 0), &b, Point::new(-2, -2)));
+        assert!(pixel_collides(&a, Point::new(5, 5), &Mask::solid(1, 1), Point::new(5, 6)));
+    }
+
+    #[test]
+    fn masks_that_do_not_overlap_do_not_collide() {
+        assert!(!pixel_collides(&Mask::solid(2, 2), Point::new(0, 0), &Mask::solid(2, 2), Point::new(2, 0)));
+    }
+
+    #[test]
+    fn mask_regions_must_fit_inside_the_surface() {
+        assert!(region_fits(Rect::new(0, 0, 4, 4), 4, 4));
+        assert!(region_fits(Rect::new(2, 1, 2, 3), 4, 4));
+        assert!(!region_fits(Rect::new(2, 2, 4, 2), 4, 4));
+        assert!(!region_fits(Rect::new(-1, 0, 2, 2), 4, 4));
+        assert!(!region_fits(Rect::new(0, 3, 1, 2), 4, 4));
+    }
+}
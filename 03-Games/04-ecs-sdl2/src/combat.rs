@@ -0,0 +1,115 @@
+use specs::prelude::*;
+use sdl2::rect::Rect;
+
+use crate::collision::{pixel_collides, Mask, SpriteMasks};
+use crate::commands::CommandBuffer;
+use crate::components::*;
+use crate::quadtree::QuadTree;
+use super::Score;
+
+pub struct Combat;
+
+impl<'a> System<'a> for Combat {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Sprite>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Enemy>,
+        ReadExpect<'a, SpriteMasks>,
+        WriteExpect<'a, Score>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, positions, sprites, projectiles, enemies, masks, mut score, lazy): Self::SystemData) {
+        // Projectiles are small enough to be treated as a single solid pixel
+        let projectile_mask = Mask::solid(1, 1);
+
+        // Index the enemies by their hitboxes so each projectile only checks the ones near it
+        let hitboxes: Vec<(Entity, Rect)> = (&entities, &positions, &sprites, &enemies).join()
+            .map(|(enemy, pos, sprite, _)| {
+                (enemy, Rect::from_center(pos.0, sprite.region.width(), sprite.region.height()))
+            })
+            .collect();
+        let bounds = hitboxes.iter()
+            .map(|&(_, hitbox)| hitbox)
+            .reduce(|bounds, hitbox| bounds.union(hitbox));
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return, // nothing to hit
+        };
+        let mut enemy_tree = QuadTree::new(bounds);
+        for &(enemy, hitbox) in &hitboxes {
+            enemy_tree.insert(enemy, hitbox);
+        }
+
+        // Deletions only take effect on world.maintain(), so track what was already hit this frame
+        let mut dead = Vec::new();
+        let mut commands = CommandBuffer::new();
+        for (projectile, projectile_pos, _) in (&entities, &positions, &projectiles).join() {
+            let candidates = enemy_tree.query(Rect::new(projectile_pos.0.x(), projectile_pos.0.y(), 1, 1));
+            for enemy in candidates {
+                if dead.contains(&enemy) {
+                    continue;
+                }
+
+                let (enemy_pos, sprite) = match (positions.get(enemy), sprites.get(enemy)) {
+                    (Some(enemy_pos), Some(sprite)) => (enemy_pos, sprite),
+                    _ => continue,
+                };
+                let hitbox = Rect::from_center(enemy_pos.0, sprite.region.width(), sprite.region.height());
+                let hit = match masks.get(sprite) {
+                    Some(mask) => pixel_collides(mask, hitbox.top_left(), &projectile_mask, projectile_pos.0),
+                    None => true,
+                };
+                if hit {
+                    dead.push(enemy);
+                    score.0 += 1;
+                    commands.despawn(enemy);
+                    commands.despawn(projectile);
+                    break;
+                }
+            }
+        }
+
+        commands.submit(&lazy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::rect::Point;
+
+    fn combat_world() -> World {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Sprite>();
+        world.register::<Projectile>();
+        world.register::<Enemy>();
+        world.add_resource(SpriteMasks::default());
+        world.add_resource(Score(0));
+        world
+    }
+
+    #[test]
+    fn hitting_an_enemy_increments_the_score_and_despawns_both() {
+        let mut world = combat_world();
+        let enemy = world.create_entity()
+            .with(Position(Point::new(0, 0)))
+            .with(Sprite {spritesheet: 1, region: Rect::new(0, 0, 32, 36)})
+            .with(Enemy)
+            .build();
+        let shot = world.create_entity().with(Position(Point::new(2, 3))).with(Projectile).build();
+        // Too far away to hit anything
+        let miss = world.create_entity().with(Position(Point::new(200, 0))).with(Projectile).build();
+
+        Combat.run_now(&world.res);
+        world.maintain();
+
+        assert_eq!(world.read_resource::<Score>().0, 1);
+        assert!(!world.is_alive(enemy));
+        assert!(!world.is_alive(shot));
+        assert!(world.is_alive(miss));
+    }
+}
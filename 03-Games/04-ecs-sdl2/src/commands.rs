@@ -0,0 +1,125 @@
+use specs::prelude::*;
+
+type Command = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// Structural changes to the world (spawning, despawning and adding components) queued up by a
+/// system while it is iterating over storages it can't change.
+///
+/// Nothing happens until the buffer is submitted to `LazyUpdate` and the world is next
+/// maintained. The commands then run one after another in the order they were queued.
+#[derive(Default)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push<F: FnOnce(&mut World) + Send + Sync + 'static>(&mut self, command: F) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Create a new entity with the components added by `build`
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn spawn<F>(&mut self, build: F)
+        where F: FnOnce(EntityBuilder) -> EntityBuilder + Send + Sync + 'static
+    {
+        self.push(move |world| {
+            build(world.create_entity()).build();
+        });
+    }
+
+    /// Delete an entity. Entities that are already gone by the time this runs are skipped.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.push(move |world| {
+            // Only fails if the entity was already deleted, which is what we wanted anyway
+            let _ = world.delete_entity(entity);
+        });
+    }
+
+    /// Add (or replace) a component on an entity, skipping entities that are gone by then
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn add_component<C: Component + Send + Sync>(&mut self, entity: Entity, component: C) {
+        self.push(move |world| {
+            // Only fails if the entity was deleted, in which case there's nothing to add it to
+            let _ = world.write_storage::<C>().insert(entity, component);
+        });
+    }
+
+    /// Hand the queued commands over to be applied at the next world.maintain()
+    pub fn submit(self, lazy: &LazyUpdate) {
+        if self.commands.is_empty() {
+            return;
+        }
+        lazy.exec_mut(move |world| {
+            for command in self.commands {
+                command(world);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::rect::Point;
+    use crate::components::{Enemy, Position};
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Enemy>();
+        world
+    }
+
+    #[test]
+    fn queued_commands_apply_in_order_at_maintain() {
+        let mut world = world();
+        let doomed = world.create_entity().with(Position(Point::new(0, 0))).build();
+        let target = world.create_entity().with(Position(Point::new(1, 1))).build();
+
+        // Queue while iterating, which can't change the storages being joined over
+        let mut commands = CommandBuffer::new();
+        {
+            let entities = world.entities();
+            let positions = world.read_storage::<Position>();
+            for (entity, _) in (&entities, &positions).join() {
+                if entity == doomed {
+                    commands.despawn(entity);
+                    // Runs after the despawn above, so it's skipped rather than panicking
+                    commands.add_component(entity, Enemy);
+                } else {
+                    commands.add_component(entity, Enemy);
+                    commands.spawn(|builder| builder.with(Position(Point::new(5, 5))));
+                }
+            }
+        }
+        commands.submit(&world.read_resource::<LazyUpdate>());
+        assert_eq!((&world.entities()).join().count(), 2);
+
+        world.maintain();
+
+        assert!(!world.is_alive(doomed));
+        assert!(world.read_storage::<Enemy>().contains(target));
+        // The spawned entity may reuse the deleted one's slot, so don't rely on join order
+        let mut positions: Vec<(i32, i32)> = world.read_storage::<Position>().join().map(|pos| (pos.0.x(), pos.0.y())).collect();
+        positions.sort();
+        assert_eq!(positions, vec![(1, 1), (5, 5)]);
+    }
+
+    #[test]
+    fn later_commands_see_earlier_ones() {
+        let mut world = world();
+        let entity = world.create_entity().build();
+
+        let mut commands = CommandBuffer::new();
+        commands.add_component(entity, Position(Point::new(1, 1)));
+        commands.add_component(entity, Position(Point::new(2, 2)));
+        commands.submit(&world.read_resource::<LazyUpdate>());
+        world.maintain();
+
+        assert_eq!(world.read_storage::<Position>().get(entity).map(|pos| pos.0), Some(Point::new(2, 2)));
+    }
+}
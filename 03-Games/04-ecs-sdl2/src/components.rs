@@ -1,7 +1,11 @@
 use specs::prelude::*;
 use specs_derive::Component;
+use sdl2::keyboard::Keycode;
 use sdl2::rect::{Point, Rect};
 use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::easing::EasingKind;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
@@ -30,6 +34,79 @@ impl Direction {
     pub const fn is_vertical(self) -> bool {
         matches!(self, Direction::Up | Direction::Down)
     }
+
+    /// Returns the direction pointing the other way
+    pub const fn opposite(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// Returns the direction after a quarter turn counter-clockwise
+    pub const fn turn_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    /// Returns the direction after a quarter turn clockwise
+    pub const fn turn_right(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// Parses a direction name such as "up" or "Left", ignoring case
+    pub fn from_str(s: &str) -> Option<Direction> {
+        match s.to_ascii_lowercase().as_str() {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// Encodes this direction as a single byte for replay and save files
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        }
+    }
+
+    /// Decodes a byte written by `to_byte`, or None if it isn't a valid direction
+    pub const fn from_byte(b: u8) -> Option<Direction> {
+        match b {
+            0 => Some(Direction::Up),
+            1 => Some(Direction::Down),
+            2 => Some(Direction::Left),
+            3 => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    /// Returns the direction for an arrow key
+    pub fn from_keycode(k: Keycode) -> Option<Direction> {
+        match k {
+            Keycode::Up => Some(Direction::Up),
+            Keycode::Down => Some(Direction::Down),
+            Keycode::Left => Some(Direction::Left),
+            Keycode::Right => Some(Direction::Right),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Component, Debug, Default)]
@@ -41,12 +118,57 @@ pub struct KeyboardControlled;
 #[storage(VecStorage)]
 pub struct Position(pub Point);
 
+/// The position of a given entity before the most recent physics step, used to smooth rendering
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct PrevPosition(pub Point);
+
 /// The current speed and direction of a given entity
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct Velocity {
     pub speed: i32,
     pub direction: Direction,
+    /// A second direction on the other axis when moving diagonally
+    pub secondary_direction: Option<Direction>,
+}
+
+impl Velocity {
+    /// Returns the combined movement delta of both directions, before applying the speed
+    pub fn offset(&self) -> (i32, i32) {
+        let (dx, dy) = self.direction.to_offset();
+        match self.secondary_direction {
+            Some(secondary) => {
+                let (sx, sy) = secondary.to_offset();
+                (dx + sx, dy + sy)
+            },
+            None => (dx, dy),
+        }
+    }
+
+    /// Rotates both directions of movement, so a diagonal stays a diagonal
+    pub fn turn(&mut self, turn: fn(Direction) -> Direction) {
+        self.direction = turn(self.direction);
+        self.secondary_direction = self.secondary_direction.map(turn);
+    }
+}
+
+/// How much longer a short-lived entity (projectile, effect, etc.) stays in the world
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Lifetime {
+    pub remaining: Duration,
+}
+
+/// Moves an entity from one position to another over time, removed once it reaches the end
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Tween {
+    pub from: Point,
+    pub to: Point,
+    pub duration: Duration,
+    pub elapsed: Duration,
+    pub easing: EasingKind,
 }
 
 #[derive(Component, Debug, Clone)]
@@ -67,8 +189,40 @@ pub struct MovementAnimation {
     pub down_frames: Vec<Sprite>,
     pub left_frames: Vec<Sprite>,
     pub right_frames: Vec<Sprite>,
+    // The standing pose for each direction, shown while the entity isn't moving
+    pub up_idle_frame: Sprite,
+    pub down_idle_frame: Sprite,
+    pub left_idle_frame: Sprite,
+    pub right_idle_frame: Sprite,
+}
+
+impl MovementAnimation {
+    /// Returns the sprite to show when the entity is stopped facing the given direction
+    pub fn idle_frame(&self, direction: Direction) -> &Sprite {
+        match direction {
+            Direction::Up => &self.up_idle_frame,
+            Direction::Down => &self.down_idle_frame,
+            Direction::Left => &self.left_idle_frame,
+            Direction::Right => &self.right_idle_frame,
+        }
+    }
 }
 
+// Projectile marker component for entities fired by the player
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct Projectile;
+
+// Enemy marker component for entities the player can defeat
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct Enemy;
+
+// Alert marker component for enemies that can currently see the player
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub struct Alert;
+
 // Player marker component to identify the player entity
 #[derive(Component, Debug, Default)]
 #[storage(NullStorage)]
@@ -118,6 +272,18 @@ impl InputHandler {
             .copied()
     }
 
+    /// The most recent active direction on the other axis from the effective direction, so that
+    /// holding e.g. Up and Right moves diagonally
+    pub fn get_secondary_direction(&self) -> Option<Direction> {
+        let effective = self.get_effective_direction()?;
+        self.direction_stack
+            .iter()
+            .find(|&&direction| {
+                direction.is_horizontal() != effective.is_horizontal() && self.is_direction_active(direction)
+            })
+            .copied()
+    }
+
     fn is_direction_active(&self, direction: Direction) -> bool {
         if direction.is_horizontal() {
             self.horizontal_balance != 0
@@ -128,3 +294,69 @@ impl InputHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Direction::*;
+
+    const ALL_DIRECTIONS: [Direction; 4] = [Up, Right, Down, Left];
+
+    #[test]
+    fn opposite_and_turns_cover_every_direction() {
+        assert_eq!(ALL_DIRECTIONS.map(Direction::opposite), [Down, Left, Up, Right]);
+        // Clockwise order, so turning right moves one step along it and turning left one step back
+        assert_eq!(ALL_DIRECTIONS.map(Direction::turn_right), [Right, Down, Left, Up]);
+        assert_eq!(ALL_DIRECTIONS.map(Direction::turn_left), [Left, Up, Right, Down]);
+        for direction in ALL_DIRECTIONS {
+            assert_eq!(direction.turn_left().turn_right(), direction);
+            assert_eq!(direction.turn_right().turn_right(), direction.opposite());
+        }
+    }
+
+    #[test]
+    fn direction_names_parse_in_any_case() {
+        assert_eq!(Direction::from_str("up"), Some(Up));
+        assert_eq!(Direction::from_str("Left"), Some(Left));
+        assert_eq!(Direction::from_str("DOWN"), Some(Down));
+        assert_eq!(Direction::from_str("rIgHt"), Some(Right));
+        assert_eq!(Direction::from_str("north"), None);
+        assert_eq!(Direction::from_str(""), None);
+        assert_eq!(Direction::from_str(" up"), None);
+    }
+
+    #[test]
+    fn only_arrow_keys_are_directions() {
+        assert_eq!(Direction::from_keycode(Keycode::Up), Some(Up));
+        assert_eq!(Direction::from_keycode(Keycode::Down), Some(Down));
+        assert_eq!(Direction::from_keycode(Keycode::Left), Some(Left));
+        assert_eq!(Direction::from_keycode(Keycode::Right), Some(Right));
+        assert_eq!(Direction::from_keycode(Keycode::W), None);
+        assert_eq!(Direction::from_keycode(Keycode::Space), None);
+    }
+
+    #[test]
+    fn direction_bytes_round_trip() {
+        for direction in ALL_DIRECTIONS {
+            assert_eq!(Direction::from_byte(direction.to_byte()), Some(direction));
+        }
+        assert_eq!(Direction::from_byte(4), None);
+        assert_eq!(Direction::from_byte(u8::MAX), None);
+    }
+
+    #[test]
+    fn turning_a_diagonal_velocity_keeps_both_axes() {
+        let turned = |turn: fn(Direction) -> Direction| {
+            let mut velocity = Velocity {speed: 20, direction: Up, secondary_direction: Some(Right)};
+            velocity.turn(turn);
+            velocity.offset()
+        };
+        assert_eq!(turned(Direction::turn_right), (1, 1));
+        assert_eq!(turned(Direction::turn_left), (-1, -1));
+        assert_eq!(turned(Direction::opposite), (-1, 1));
+
+        let mut straight = Velocity {speed: 20, direction: Up, secondary_direction: None};
+        straight.turn(Direction::turn_right);
+        assert_eq!(straight.offset(), (1, 0));
+    }
+}
@@ -0,0 +1,76 @@
+// Easing curves for animations, camera movement and UI transitions. Every function takes a
+// progress value `t` in [0, 1] and returns the eased progress, with 0 mapping to 0 and 1 to 1.
+
+/// Constant speed from start to end
+pub fn linear(t: f32) -> f32 {
+    t
+}
+
+/// Starts slow and speeds up towards the end
+pub fn ease_in_quad(t: f32) -> f32 {
+    t * t
+}
+
+/// Starts fast and slows down towards the end
+pub fn ease_out_quad(t: f32) -> f32 {
+    t * (2.0 - t)
+}
+
+/// Starts slow, speeds up through the middle and slows down again at the end
+pub fn ease_in_out_quad(t: f32) -> f32 {
+    if t < 0.5 {
+        2.0 * t * t
+    } else {
+        -1.0 + (4.0 - 2.0 * t) * t
+    }
+}
+
+/// Selects one of the easing curves above so it can be stored in components
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EasingKind {
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+}
+
+impl EasingKind {
+    /// Applies the selected curve to the progress `t`
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            EasingKind::Linear => linear(t),
+            EasingKind::EaseInQuad => ease_in_quad(t),
+            EasingKind::EaseOutQuad => ease_out_quad(t),
+            EasingKind::EaseInOutQuad => ease_in_out_quad(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [EasingKind; 4] = [
+        EasingKind::Linear,
+        EasingKind::EaseInQuad,
+        EasingKind::EaseOutQuad,
+        EasingKind::EaseInOutQuad,
+    ];
+
+    #[test]
+    fn every_curve_starts_at_zero_and_ends_at_one() {
+        for kind in KINDS {
+            assert_eq!(kind.apply(0.0), 0.0, "{:?}", kind);
+            assert_eq!(kind.apply(1.0), 1.0, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn every_curve_increases_through_the_midpoint() {
+        for kind in KINDS {
+            let (before, mid, after) = (kind.apply(0.49), kind.apply(0.5), kind.apply(0.51));
+            assert!(before < mid && mid < after, "{:?}: {} {} {}", kind, before, mid, after);
+        }
+        assert_eq!(ease_in_out_quad(0.5), 0.5);
+    }
+}
@@ -0,0 +1,300 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// A fixed-size 2D grid of cells stored row by row, e.g. the tiles of a level
+#[derive(Debug, Clone)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// Create a grid with every cell set to `fill`
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Self {width, height, cells: vec![fill; width * height]}
+    }
+}
+
+impl<T> Grid<T> {
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the cell at (x, y), or None if it is outside the grid
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    /// Replaces the cell at (x, y) and returns its old value, or None if it is outside the grid
+    pub fn set(&mut self, x: usize, y: usize, value: T) -> Option<T> {
+        let i = self.index(x, y)?;
+        Some(std::mem::replace(&mut self.cells[i], value))
+    }
+
+    /// The cells directly above, below, left and right of (x, y) that are inside the grid
+    pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut neighbors = Vec::with_capacity(4);
+        if self.index(x, y).is_none() {
+            return neighbors;
+        }
+        if y > 0 {
+            neighbors.push((x, y - 1));
+        }
+        if y + 1 < self.height {
+            neighbors.push((x, y + 1));
+        }
+        if x > 0 {
+            neighbors.push((x - 1, y));
+        }
+        if x + 1 < self.width {
+            neighbors.push((x + 1, y));
+        }
+        neighbors
+    }
+
+    /// Every cell that can be reached from `start` by stepping between neighboring passable cells,
+    /// including `start` itself. Empty if `start` isn't passable or is outside the grid.
+    pub fn flood_fill<F: Fn(&T) -> bool>(&self, start: (usize, usize), passable: F) -> HashSet<(usize, usize)> {
+        let mut reached = HashSet::new();
+        if !self.get(start.0, start.1).is_some_and(&passable) {
+            return reached;
+        }
+        reached.insert(start);
+        let mut frontier = vec![start];
+        while let Some((x, y)) = frontier.pop() {
+            for (nx, ny) in self.neighbors(x, y) {
+                if self.get(nx, ny).is_some_and(&passable) && reached.insert((nx, ny)) {
+                    frontier.push((nx, ny));
+                }
+            }
+        }
+        reached
+    }
+
+    /// True if every cell strictly between `from` and `to` on a straight (Bresenham) line is
+    /// transparent. Cells outside the grid block the line.
+    pub fn line_of_sight<F: Fn(&T) -> bool>(&self, from: (i32, i32), to: (i32, i32), transparent: F) -> bool {
+        let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+        let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        let mut error = dx + dy;
+        let (mut x, mut y) = from;
+        loop {
+            if (x, y) == to {
+                return true;
+            }
+            if (x, y) != from {
+                let cell = if x < 0 || y < 0 { None } else { self.get(x as usize, y as usize) };
+                if !cell.is_some_and(&transparent) {
+                    return false;
+                }
+            }
+            let doubled_error = 2 * error;
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// The cheapest path from `start` to `goal`, including both ends, or None if the goal can't
+    /// be reached. `cost` gives the cost of stepping onto a cell, or None if it can't be entered.
+    /// Uses A* with the Manhattan distance as its heuristic, so costs must be at least 1 for it to
+    /// never overestimate.
+    pub fn astar<C: Fn(&T) -> Option<u32>>(&self, start: (usize, usize), goal: (usize, usize), cost: C) -> Option<Vec<(usize, usize)>> {
+        let step_cost = |(x, y): (usize, usize)| self.get(x, y).and_then(&cost);
+        if step_cost(goal).is_none() || self.get(start.0, start.1).is_none() {
+            return None;
+        }
+        let heuristic = |(x, y): (usize, usize)| (x.abs_diff(goal.0) + y.abs_diff(goal.1)) as u32;
+
+        // Cheapest known cost to reach each cell and the cell we reached it from
+        let mut costs = HashMap::from([(start, 0)]);
+        let mut came_from = HashMap::new();
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), start))]);
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = vec![goal];
+                while let Some(&previous) = came_from.get(path.last().expect("path starts non-empty")) {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.neighbors(cell.0, cell.1) {
+                let total = match step_cost(next) {
+                    Some(step) => costs[&cell] + step,
+                    None => continue,
+                };
+                if costs.get(&next).is_none_or(|&known| total < known) {
+                    costs.insert(next, total);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((total + heuristic(next), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a grid of characters from rows of text, e.g. '#' for walls and '.' for floor
+    fn grid_from(rows: &[&str]) -> Grid<char> {
+        let mut grid = Grid::new(rows[0].len(), rows.len(), ' ');
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                grid.set(x, y, c);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn cells_are_read_and_written_by_coordinate() {
+        let mut grid = Grid::new(3, 2, 0);
+        assert_eq!(grid.set(2, 1, 7), Some(0));
+        assert_eq!(grid.get(2, 1), Some(&7));
+        assert_eq!(grid.get(1, 1), Some(&0));
+        assert_eq!(grid.set(2, 1, 8), Some(7));
+    }
+
+    #[test]
+    fn out_of_bounds_cells_are_none() {
+        let mut grid = Grid::new(3, 2, 0);
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.get(0, 2), None);
+        assert_eq!(grid.set(3, 1, 5), None);
+        assert!(grid.neighbors(5, 5).is_empty());
+    }
+
+    #[test]
+    fn corner_cells_have_two_neighbors() {
+        let grid = Grid::new(3, 3, 0);
+        assert_eq!(grid.neighbors(0, 0), [(0, 1), (1, 0)]);
+        assert_eq!(grid.neighbors(2, 2), [(2, 1), (1, 2)]);
+        assert_eq!(grid.neighbors(1, 1).len(), 4);
+    }
+
+    #[test]
+    fn flood_fill_stays_on_its_side_of_a_wall() {
+        let grid = grid_from(&[
+            "..#..",
+            "..#..",
+            "..#..",
+        ]);
+        let left = grid.flood_fill((0, 0), |&c| c == '.');
+        assert_eq!(left.len(), 6);
+        assert!(left.iter().all(|&(x, _)| x < 2));
+        assert_eq!(grid.flood_fill((4, 2), |&c| c == '.').len(), 6);
+        assert!(grid.flood_fill((2, 0), |&c| c == '.').is_empty());
+    }
+
+    #[test]
+    fn flood_fill_goes_through_gaps() {
+        let grid = grid_from(&[
+            "..#..",
+            ".....",
+            "..#..",
+        ]);
+        assert_eq!(grid.flood_fill((0, 0), |&c| c == '.').len(), 13);
+    }
+
+    #[test]
+    fn line_of_sight_is_clear_across_open_floor() {
+        let grid = grid_from(&[
+            ".....",
+            ".....",
+            ".....",
+        ]);
+        assert!(grid.line_of_sight((0, 0), (4, 2), |&c| c == '.'));
+        assert!(grid.line_of_sight((4, 0), (0, 0), |&c| c == '.'));
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_by_a_wall_between_the_ends() {
+        let grid = grid_from(&[
+            ".....",
+            "..#..",
+            ".....",
+        ]);
+        assert!(!grid.line_of_sight((0, 1), (4, 1), |&c| c == '.'));
+        assert!(!grid.line_of_sight((0, 0), (4, 2), |&c| c == '.'));
+        // Walls at either end don't block, only ones in between
+        assert!(grid.line_of_sight((2, 1), (4, 1), |&c| c == '.'));
+        // Going around the wall along the top row is fine
+        assert!(grid.line_of_sight((0, 0), (4, 0), |&c| c == '.'));
+    }
+
+    fn walkable(c: &char) -> Option<u32> {
+        if *c == '#' { None } else { Some(1) }
+    }
+
+    #[test]
+    fn astar_finds_a_shortest_path() {
+        let grid = grid_from(&[
+            ".....",
+            ".....",
+            ".....",
+        ]);
+        let path = grid.astar((0, 0), (4, 2), walkable).unwrap();
+        // Manhattan distance 6, so 7 cells including both ends
+        assert_eq!(path.len(), 7);
+        assert_eq!((path[0], path[6]), ((0, 0), (4, 2)));
+        assert_eq!(grid.astar((1, 1), (1, 1), walkable), Some(vec![(1, 1)]));
+    }
+
+    #[test]
+    fn astar_routes_around_walls() {
+        let grid = grid_from(&[
+            "..#..",
+            "..#..",
+            ".....",
+        ]);
+        let path = grid.astar((0, 0), (4, 0), walkable).unwrap();
+        // Down to the gap on the bottom row and back up: 4 across plus 2 down and 2 up
+        assert_eq!(path.len(), 9);
+        assert!(path.iter().all(|&(x, y)| grid.get(x, y) == Some(&'.')));
+        assert!(path.windows(2).all(|step| grid.neighbors(step[0].0, step[0].1).contains(&step[1])));
+    }
+
+    #[test]
+    fn astar_gives_up_on_unreachable_goals() {
+        let grid = grid_from(&[
+            "..#..",
+            "..#..",
+        ]);
+        assert_eq!(grid.astar((0, 0), (4, 0), walkable), None);
+        assert_eq!(grid.astar((0, 0), (2, 0), walkable), None);
+    }
+
+    #[test]
+    fn astar_prefers_a_longer_cheap_path_over_a_short_muddy_one() {
+        let grid = grid_from(&[
+            ".~~~.",
+            ".....",
+        ]);
+        let cost = |c: &char| match c {
+            '#' => None,
+            '~' => Some(5),
+            _ => Some(1),
+        };
+        let path = grid.astar((0, 0), (4, 0), cost).unwrap();
+        // Through the mud is 5 cells but costs 16; around it is 7 cells costing 6
+        assert_eq!(path.len(), 7);
+        assert!(path.iter().all(|&(x, y)| grid.get(x, y) != Some(&'~')));
+
+        // With flat costs the straight line through wins again
+        assert_eq!(grid.astar((0, 0), (4, 0), |_| Some(1)).map(|path| path.len()), Some(5));
+    }
+}
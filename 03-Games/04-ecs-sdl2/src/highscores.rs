@@ -0,0 +1,68 @@
+use std::cmp::Reverse;
+use std::fs;
+
+/// Only this many of the best scores are kept
+const MAX_HIGH_SCORES: usize = 10;
+
+/// Best first, cut down to the top `MAX_HIGH_SCORES`. Equal scores keep their existing order.
+fn top_scores(scores: &[(String, u32)]) -> Vec<(String, u32)> {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by_key(|(_, score)| Reverse(*score));
+    sorted.truncate(MAX_HIGH_SCORES);
+    sorted
+}
+
+/// Write the best scores to `path`, one `<score> <name>` line each
+pub fn save_high_scores(scores: &[(String, u32)], path: &str) -> Result<(), String> {
+    let out: String = top_scores(scores).iter()
+        .map(|(name, score)| format!("{} {}\n", score, name))
+        .collect();
+    fs::write(path, out).map_err(|e| format!("Could not write high scores to {}: {}", path, e))
+}
+
+/// Read back the scores written by `save_high_scores`. A missing file means there are no high
+/// scores yet, and lines that can't be parsed are skipped.
+pub fn load_high_scores(path: &str) -> Vec<(String, u32)> {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let scores: Vec<(String, u32)> = contents.lines()
+        .filter_map(|line| {
+            let (score, name) = line.split_once(' ')?;
+            Some((name.to_string(), score.parse().ok()?))
+        })
+        .collect();
+    top_scores(&scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("ecs-sdl2-{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn scores_round_trip_sorted_and_capped() {
+        let path = temp_path("high-scores");
+        let scores: Vec<(String, u32)> = (1..=12)
+            .map(|i| (format!("Player {}", i), (i * 37) % 100))
+            .collect();
+        save_high_scores(&scores, &path).unwrap();
+        let loaded = load_high_scores(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), MAX_HIGH_SCORES);
+        assert!(loaded.windows(2).all(|pair| pair[0].1 >= pair[1].1), "{:?}", loaded);
+        // Names with spaces survive, and the two lowest scores (11 and 7) were dropped
+        assert_eq!(loaded[0], ("Player 8".to_string(), 96));
+        assert_eq!(loaded.last().unwrap(), &("Player 6".to_string(), 22));
+    }
+
+    #[test]
+    fn missing_file_has_no_scores() {
+        assert!(load_high_scores(&temp_path("no-such-file")).is_empty());
+    }
+}
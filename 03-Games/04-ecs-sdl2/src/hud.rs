@@ -0,0 +1,90 @@
+use sdl2::rect::{Point, Rect};
+
+/// Which of the seven segments are lit for each digit, in the order:
+/// top, top-right, bottom-right, bottom, bottom-left, top-left, middle
+const DIGIT_SEGMENTS: [[bool; 7]; 10] = [
+    [true, true, true, true, true, true, false],
+    [false, true, true, false, false, false, false],
+    [true, true, false, true, true, false, true],
+    [true, true, true, true, false, false, true],
+    [false, true, true, false, false, true, true],
+    [true, false, true, true, false, true, true],
+    [true, false, true, true, true, true, true],
+    [true, true, true, false, false, false, false],
+    [true, true, true, true, true, true, true],
+    [true, true, true, true, false, true, true],
+];
+
+/// Width of a single digit in pixels (its height is twice this)
+const DIGIT_WIDTH: u32 = 12;
+/// Thickness of each segment in pixels
+const SEGMENT_THICKNESS: u32 = 2;
+/// Horizontal gap between two digits in pixels
+const DIGIT_SPACING: u32 = 4;
+
+/// Splits a number into its decimal digits, most significant first
+pub fn digits(mut value: u32) -> Vec<u8> {
+    let mut digits = vec![(value % 10) as u8];
+    value /= 10;
+    while value > 0 {
+        digits.push((value % 10) as u8);
+        value /= 10;
+    }
+    digits.reverse();
+    digits
+}
+
+/// Lays out the lit segments of a number as seven-segment digits, starting with the top-left
+/// corner of the first digit at `origin`
+pub fn number_segments(value: u32, origin: Point) -> Vec<Rect> {
+    let w = DIGIT_WIDTH;
+    let t = SEGMENT_THICKNESS;
+    let mut rects = Vec::new();
+
+    for (i, &digit) in digits(value).iter().enumerate() {
+        let x = origin.x() + ((w + DIGIT_SPACING) * i as u32) as i32;
+        let y = origin.y();
+        let (wi, ti) = (w as i32, t as i32);
+
+        let segments = [
+            Rect::new(x, y, w, t),
+            Rect::new(x + wi - ti, y, t, w),
+            Rect::new(x + wi - ti, y + wi, t, w),
+            Rect::new(x, y + 2 * wi - ti, w, t),
+            Rect::new(x, y + wi, t, w),
+            Rect::new(x, y, t, w),
+            Rect::new(x, y + wi - ti / 2, w, t),
+        ];
+        for (segment, &lit) in segments.iter().zip(DIGIT_SEGMENTS[digit as usize].iter()) {
+            if lit {
+                rects.push(*segment);
+            }
+        }
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_are_most_significant_first() {
+        assert_eq!(digits(0), vec![0]);
+        assert_eq!(digits(7), vec![7]);
+        assert_eq!(digits(1203), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn each_digit_lights_its_segments_side_by_side() {
+        // "1" lights the two right-hand segments
+        let one = number_segments(1, Point::new(10, 20));
+        assert_eq!(one, vec![Rect::new(20, 20, 2, 12), Rect::new(20, 32, 2, 12)]);
+
+        // "8" lights all seven, and the second digit starts one digit plus its spacing along
+        let eighty_eight = number_segments(88, Point::new(0, 0));
+        assert_eq!(eighty_eight.len(), 14);
+        assert_eq!(eighty_eight[7], Rect::new(16, 0, 12, 2));
+    }
+}
@@ -0,0 +1,64 @@
+use specs::prelude::*;
+use std::fmt::Write;
+
+use crate::components::*;
+
+/// Lists every entity with its Position, Velocity and Sprite region, one entity per line, e.g.
+/// `Entity 0 (gen 1): position=(0, 0) velocity=20 Right sprite=sheet 0 (0, 72, 26x36)`
+pub fn dump_world(world: &World) -> String {
+    let entities = world.entities();
+    let positions = world.read_storage::<Position>();
+    let velocities = world.read_storage::<Velocity>();
+    let sprites = world.read_storage::<Sprite>();
+
+    let mut dump = String::new();
+    for entity in (&entities).join() {
+        // Writing to a String can't fail
+        write!(dump, "Entity {} (gen {}):", entity.id(), entity.gen().id()).unwrap();
+        match positions.get(entity) {
+            Some(position) => write!(dump, " position=({}, {})", position.0.x(), position.0.y()).unwrap(),
+            None => dump.push_str(" position=none"),
+        }
+        match velocities.get(entity) {
+            Some(velocity) => write!(dump, " velocity={} {:?}", velocity.speed, velocity.direction).unwrap(),
+            None => dump.push_str(" velocity=none"),
+        }
+        match sprites.get(entity) {
+            Some(sprite) => {
+                let region = sprite.region;
+                write!(dump, " sprite=sheet {} ({}, {}, {}x{})",
+                    sprite.spritesheet, region.x(), region.y(), region.width(), region.height()).unwrap();
+            },
+            None => dump.push_str(" sprite=none"),
+        }
+        dump.push('\n');
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::rect::{Point, Rect};
+
+    #[test]
+    fn dump_lists_each_entity_with_its_components() {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.register::<Sprite>();
+        world.create_entity()
+            .with(Position(Point::new(0, 0)))
+            .with(Velocity {speed: 20, direction: Direction::Right, secondary_direction: None})
+            .with(Sprite {spritesheet: 0, region: Rect::new(0, 72, 26, 36)})
+            .build();
+        world.create_entity().with(Position(Point::new(-150, 150))).build();
+
+        let dump = dump_world(&world);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines, [
+            "Entity 0 (gen 1): position=(0, 0) velocity=20 Right sprite=sheet 0 (0, 72, 26x36)",
+            "Entity 1 (gen 1): position=(-150, 150) velocity=none sprite=none",
+        ]);
+    }
+}
@@ -1,6 +1,6 @@
 use specs::prelude::*;
 use crate::components::*;
-use super::MovementCommand;
+use super::{InputSnapshot, MovementCommand};
 
 const PLAYER_MOVEMENT_SPEED: i32 = 20;
 
@@ -8,14 +8,14 @@ pub struct Keyboard;
 
 impl<'a> System<'a> for Keyboard {
     type SystemData = (
-        ReadExpect<'a, Option<MovementCommand>>,
+        ReadExpect<'a, InputSnapshot>,
         ReadStorage<'a, KeyboardControlled>,
         WriteStorage<'a, Velocity>,
     );
 
-    fn run(&mut self, (movement_command, keyboard_controlled, mut velocities): Self::SystemData) {
+    fn run(&mut self, (input, keyboard_controlled, mut velocities): Self::SystemData) {
         // Get the movement command if there is one
-        let movement_command = match &*movement_command {
+        let movement_command = match &input.movement {
             Some(movement_command) => movement_command,
             None => return, // no change
         };
@@ -25,9 +25,48 @@ impl<'a> System<'a> for Keyboard {
                 &MovementCommand::Move(direction) => {
                     velocity.speed = PLAYER_MOVEMENT_SPEED;
                     velocity.direction = direction;
+                    velocity.secondary_direction = None;
+                },
+                &MovementCommand::MoveMultiple(direction, secondary_direction) => {
+                    velocity.speed = PLAYER_MOVEMENT_SPEED;
+                    velocity.direction = direction;
+                    velocity.secondary_direction = Some(secondary_direction);
                 },
                 MovementCommand::Stop => velocity.speed = 0,
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Direction::*;
+
+    fn run_command(command: MovementCommand) -> (i32, (i32, i32)) {
+        let mut world = World::new();
+        world.register::<KeyboardControlled>();
+        world.register::<Velocity>();
+        world.add_resource(InputSnapshot {movement: Some(command)});
+        let player = world.create_entity()
+            .with(KeyboardControlled)
+            .with(Velocity {speed: 0, direction: Down, secondary_direction: None})
+            .build();
+
+        Keyboard.run_now(&world.res);
+        let velocities = world.read_storage::<Velocity>();
+        let velocity = velocities.get(player).unwrap();
+        (velocity.speed, velocity.offset())
+    }
+
+    #[test]
+    fn move_multiple_combines_both_directions() {
+        assert_eq!(run_command(MovementCommand::MoveMultiple(Up, Right)), (PLAYER_MOVEMENT_SPEED, (1, -1)));
+    }
+
+    #[test]
+    fn single_move_clears_the_second_direction() {
+        assert_eq!(run_command(MovementCommand::Move(Left)), (PLAYER_MOVEMENT_SPEED, (-1, 0)));
+        assert_eq!(run_command(MovementCommand::Stop).0, 0);
+    }
+}
@@ -0,0 +1,135 @@
+use sdl2::rect::{Point, Rect};
+
+use crate::grid::Grid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Floor,
+    /// Can be crossed, but slowly
+    Mud,
+    Wall,
+}
+
+impl Tile {
+    /// How hard the tile is to cross, or None if it can't be entered at all
+    pub const fn move_cost(self) -> Option<u32> {
+        match self {
+            Tile::Floor => Some(1),
+            Tile::Mud => Some(3),
+            Tile::Wall => None,
+        }
+    }
+}
+
+/// The width and height of a tile in pixels
+pub const TILE_SIZE: i32 = 40;
+
+/// The tiles of the level, centered on (0, 0) like everything else in the world
+pub struct Level {
+    pub tiles: Grid<Tile>,
+}
+
+impl Level {
+    /// Build a level from rows of text where `#` is a wall, `~` is mud and anything else is floor
+    pub fn from_map(rows: &[&str]) -> Result<Level, String> {
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut tiles = Grid::new(width, rows.len(), Tile::Floor);
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(format!("level row {} is {} tiles wide, expected {}", y, row.len(), width));
+            }
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '#' => tiles.set(x, y, Tile::Wall),
+                    '~' => tiles.set(x, y, Tile::Mud),
+                    _ => None,
+                };
+            }
+        }
+
+        // Every open tile must be reachable from every other one. A walled off pocket can never be
+        // visited, which is always a map mistake.
+        let floors: Vec<_> = (0..tiles.height)
+            .flat_map(|y| (0..tiles.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| tiles.get(x, y) != Some(&Tile::Wall))
+            .collect();
+        if let Some(&start) = floors.first() {
+            let reachable = tiles.flood_fill(start, |&tile| tile != Tile::Wall);
+            if let Some(&(x, y)) = floors.iter().find(|cell| !reachable.contains(cell)) {
+                return Err(format!("floor tile ({}, {}) can't be reached from ({}, {})", x, y, start.0, start.1));
+            }
+        }
+        Ok(Level {tiles})
+    }
+
+    /// Half the size of the level in pixels, i.e. the offset from the world origin to its corner
+    fn half_extent(&self) -> Point {
+        Point::new(self.tiles.width as i32 * TILE_SIZE / 2, self.tiles.height as i32 * TILE_SIZE / 2)
+    }
+
+    /// The tile containing the given world position, or None if it is outside the level
+    pub fn cell_at(&self, position: Point) -> Option<(usize, usize)> {
+        let local = position + self.half_extent();
+        if local.x() < 0 || local.y() < 0 {
+            return None;
+        }
+        let cell = ((local.x() / TILE_SIZE) as usize, (local.y() / TILE_SIZE) as usize);
+        self.tiles.get(cell.0, cell.1).map(|_| cell)
+    }
+
+    /// The tile coordinates of a world position, which may be outside the level
+    fn cell_coords(&self, position: Point) -> (i32, i32) {
+        let local = position + self.half_extent();
+        (local.x().div_euclid(TILE_SIZE), local.y().div_euclid(TILE_SIZE))
+    }
+
+    /// True if no walls lie between the tiles containing the two world positions
+    pub fn can_see(&self, from: Point, to: Point) -> bool {
+        self.tiles.line_of_sight(self.cell_coords(from), self.cell_coords(to), |&tile| tile != Tile::Wall)
+    }
+
+    /// The center of the tile at (x, y) in world coordinates
+    pub fn cell_center(&self, x: usize, y: usize) -> Point {
+        self.cell_rect(x, y).center()
+    }
+
+    /// The quickest tiles to walk through to get from one world position to another, starting
+    /// with the tile containing `from`, or None if there is no way through. Goes around mud when
+    /// that's faster than wading through it.
+    pub fn find_path(&self, from: Point, to: Point) -> Option<Vec<(usize, usize)>> {
+        let start = self.cell_at(from)?;
+        let goal = self.cell_at(to)?;
+        self.tiles.astar(start, goal, |&tile| tile.move_cost())
+    }
+
+    /// The tile at a world position. Everything outside the level counts as floor.
+    pub fn tile_at(&self, position: Point) -> Tile {
+        self.cell_at(position)
+            .and_then(|(x, y)| self.tiles.get(x, y).copied())
+            .unwrap_or(Tile::Floor)
+    }
+
+    /// True if the given world position is inside a wall. Everything outside the level is open.
+    pub fn is_wall(&self, position: Point) -> bool {
+        self.tile_at(position) == Tile::Wall
+    }
+
+    /// The area covered by the tile at (x, y) in world coordinates
+    pub fn cell_rect(&self, x: usize, y: usize) -> Rect {
+        let corner = Point::new(x as i32 * TILE_SIZE, y as i32 * TILE_SIZE) - self.half_extent();
+        Rect::new(corner.x(), corner.y(), TILE_SIZE as u32, TILE_SIZE as u32)
+    }
+
+    /// The areas covered by every tile of the given kind, in world coordinates
+    pub fn tiles_of(&self, kind: Tile) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        for y in 0..self.tiles.height {
+            for x in 0..self.tiles.width {
+                if self.tiles.get(x, y) == Some(&kind) {
+                    rects.push(self.cell_rect(x, y));
+                }
+            }
+        }
+        rects
+    }
+}
@@ -0,0 +1,51 @@
+use specs::prelude::*;
+use std::time::Duration;
+
+use crate::components::*;
+use super::DeltaTime;
+
+pub struct LifetimeSystem;
+
+impl<'a> System<'a> for LifetimeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, DeltaTime>,
+        WriteStorage<'a, Lifetime>,
+    );
+
+    fn run(&mut self, (entities, delta_time, mut lifetimes): Self::SystemData) {
+        for (entity, lifetime) in (&entities, &mut lifetimes).join() {
+            lifetime.remaining = lifetime.remaining.saturating_sub(delta_time.0);
+            if lifetime.remaining == Duration::ZERO {
+                // The entity is actually removed on the next call to world.maintain()
+                entities.delete(entity).expect("entity from join should be alive");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_is_deleted_once_its_lifetime_runs_out() {
+        let mut world = World::new();
+        world.register::<Lifetime>();
+        world.add_resource(DeltaTime(Duration::from_millis(40)));
+        let entity = world.create_entity()
+            .with(Lifetime {remaining: Duration::from_millis(100)})
+            .build();
+
+        // 80ms in, with 20ms left
+        for _ in 0..2 {
+            LifetimeSystem.run_now(&world.res);
+            world.maintain();
+        }
+        assert!(world.is_alive(entity));
+
+        LifetimeSystem.run_now(&world.res);
+        world.maintain();
+        assert!(!world.is_alive(entity));
+    }
+}
@@ -3,24 +3,115 @@ mod physics;
 mod animator;
 mod keyboard;
 mod renderer;
+mod lifetime;
+mod combat;
+mod hud;
+mod easing;
+mod tween;
+mod collision;
+mod quadtree;
+mod scene;
+mod inspector;
+mod replay;
+mod grid;
+mod level;
+mod vision;
+mod chase;
+mod commands;
+mod timer;
+mod highscores;
+mod ratelimit;
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
 // "self" imports the "image" module itself as well as everything else we listed
-use sdl2::image::{self, LoadTexture, InitFlag};
+use sdl2::image::{self, LoadSurface, LoadTexture, InitFlag};
+use sdl2::surface::Surface;
 
 use specs::prelude::*;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use crate::collision::{Mask, SpriteMasks};
 use crate::components::*;
+use crate::easing::EasingKind;
+use crate::level::Level;
+use crate::ratelimit::{Debouncer, Throttle};
+use crate::replay::Replay;
+use crate::timer::Timer;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MovementCommand {
     Stop,
     Move(Direction),
+    /// Move diagonally: a direction plus a second direction on the other axis
+    MoveMultiple(Direction, Direction),
 }
 
+impl MovementCommand {
+    /// Commands with a higher priority win when several arrive together
+    const fn priority(self) -> u8 {
+        match self {
+            MovementCommand::Stop => 2,
+            MovementCommand::MoveMultiple(..) => 1,
+            MovementCommand::Move(_) => 0,
+        }
+    }
+}
+
+/// The player's input for the current physics step. It is captured once before the systems run,
+/// so every system sees the same input no matter what events arrive while they are running.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputSnapshot {
+    /// None - no change, Some(MovementCommand) - perform movement
+    pub movement: Option<MovementCommand>,
+}
+
+/// The amount of time that passed since the previous frame
+pub struct DeltaTime(pub Duration);
+
+/// The player's current score, increased whenever an enemy is defeated
+pub struct Score(pub u32);
+
+const PROJECTILE_SPEED: i32 = 30;
+const PROJECTILE_LIFETIME: Duration = Duration::from_secs(1);
+const ENEMY_ENTRANCE_DURATION: Duration = Duration::from_secs(1);
+const SCENE_FILE: &str = "scene.txt";
+const REPLAY_FILE: &str = "replay.bin";
+const HIGH_SCORES_FILE: &str = "highscores.txt";
+/// How long the player has to finish a level before it restarts
+const LEVEL_TIME: Duration = Duration::from_secs(120);
+// The game logic always advances in steps of this size, no matter how fast we render
+const PHYSICS_STEP: Duration = Duration::from_millis(50);
+// If rendering stalls, give up on catching up after this many steps in a single frame
+const MAX_STEPS_PER_FRAME: u32 = 5;
+const FRAME_RATE: u32 = 60;
+/// Turn keys pressed faster than this are ignored, so mashing them doesn't spin the player around
+const TURN_DEBOUNCE: Duration = Duration::from_millis(150);
+/// Holding fire shoots at most this often
+const FIRE_INTERVAL: Duration = Duration::from_millis(250);
+
+// '#' is a wall and '~' is mud. The player starts in the middle and the enemies in the four
+// open quarters.
+const LEVEL_MAP: [&str; 15] = [
+    "####################",
+    "#.......~~~~.......#",
+    "#.......~~~~.......#",
+    "#.......~~~~.......#",
+    "#...####....####...#",
+    "#..................#",
+    "#..~~..........~~..#",
+    "#..~~..........~~..#",
+    "#..~~..........~~..#",
+    "#..................#",
+    "#...####....####...#",
+    "#.......~~~~.......#",
+    "#.......~~~~.......#",
+    "#.......~~~~.......#",
+    "####################",
+];
+
 /// Returns the row of the spritesheet corresponding to the given direction
 fn direction_spritesheet_row(direction: Direction) -> i32 {
     use self::Direction::*;
@@ -32,6 +123,44 @@ fn direction_spritesheet_row(direction: Direction) -> i32 {
     }
 }
 
+/// Returns the movement command for the arrow keys currently held down
+fn command_for_input(input: &InputHandler) -> MovementCommand {
+    match (input.get_effective_direction(), input.get_secondary_direction()) {
+        (Some(direction), Some(secondary_direction)) => MovementCommand::MoveMultiple(direction, secondary_direction),
+        (Some(direction), None) => MovementCommand::Move(direction),
+        (None, _) => MovementCommand::Stop,
+    }
+}
+
+/// Picks the command to act on when several arrive in the same frame, so the result doesn't depend
+/// on event order: Stop beats any movement and a diagonal move beats a straight one. Between
+/// commands of equal priority the latest wins.
+fn resolve_commands(cmds: &[MovementCommand]) -> Option<MovementCommand> {
+    cmds.iter()
+        .copied()
+        .enumerate()
+        .max_by_key(|&(i, command)| (command.priority(), i))
+        .map(|(_, command)| command)
+}
+
+/// Updates the held arrow keys for a keyboard event and returns the resulting movement command,
+/// or None if the event doesn't affect movement
+fn translate_event(event: &Event, state: &mut InputHandler) -> Option<MovementCommand> {
+    let (keycode, pressed) = match *event {
+        Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => (keycode, true),
+        Event::KeyUp { keycode: Some(keycode), repeat: false, .. } => (keycode, false),
+        _ => return None,
+    };
+    let direction = Direction::from_keycode(keycode)?;
+    
+    if pressed {
+        state.press_key(direction);
+    } else {
+        state.release_key(direction);
+    }
+    Some(command_for_input(state))
+}
+
 /// Create animation frames for the standard character spritesheet
 fn character_animation_frames(spritesheet: usize, top_left_frame: Rect, direction: Direction) -> Vec<Sprite> {
     // All assumptions about the spritesheets are now encapsulated in this function instead of in
@@ -55,6 +184,176 @@ fn character_animation_frames(spritesheet: usize, top_left_frame: Rect, directio
     frames
 }
 
+/// Fire a projectile from the given point, travelling in the given direction until it expires
+fn spawn_projectile(world: &mut World, from: Point, dir: Direction) {
+    world.create_entity()
+        .with(Projectile)
+        .with(Position(from))
+        .with(Velocity {speed: PROJECTILE_SPEED, direction: dir, secondary_direction: None})
+        .with(Lifetime {remaining: PROJECTILE_LIFETIME})
+        .build();
+}
+
+/// Fire from wherever the player is, in the direction their sprite is facing
+fn fire_from_player(world: &mut World) {
+    let shot = {
+        let (positions, velocities, players): (ReadStorage<Position>, ReadStorage<Velocity>, ReadStorage<Player>) = world.system_data();
+        (&positions, &velocities, &players).join()
+            .map(|(pos, vel, _)| (pos.0, animator::facing(vel)))
+            .next()
+    };
+    if let Some((from, dir)) = shot {
+        spawn_projectile(world, from, dir);
+    }
+}
+
+/// The walking animation used by the player
+fn player_animation() -> MovementAnimation {
+    // First texture in textures array
+    let player_spritesheet = 0;
+    let player_top_left_frame = Rect::new(0, 0, 26, 36);
+    
+    let up_frames = character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Up);
+    let down_frames = character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Down);
+    let left_frames = character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Left);
+    let right_frames = character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Right);
+    
+    MovementAnimation {
+        current_frame: 0,
+        up_idle_frame: up_frames[0].clone(),
+        down_idle_frame: down_frames[0].clone(),
+        left_idle_frame: left_frames[0].clone(),
+        right_idle_frame: right_frames[0].clone(),
+        up_frames,
+        down_frames,
+        left_frames,
+        right_frames,
+    }
+}
+
+/// Create the player entity at the center of the screen
+fn initialize_player(world: &mut World) {
+    let player_animation = player_animation();
+    
+    world.create_entity()
+        .with(KeyboardControlled)
+        .with(Position(Point::new(0, 0)))
+        .with(Velocity {speed: 0, direction: Direction::Right, secondary_direction: None})
+        .with(player_animation.idle_frame(Direction::Right).clone())
+        .with(player_animation)
+        .with(Player) // Add Player component
+        .build();
+}
+
+/// The animation frames used by every enemy
+fn enemy_frames() -> Vec<Sprite> {
+    // Second texture in textures array
+    let enemy_spritesheet = 1;
+    let enemy_top_left_frame = Rect::new(0, 0, 32, 36);
+    
+    character_animation_frames(enemy_spritesheet, enemy_top_left_frame, Direction::Down)
+}
+
+/// Create the enemies, each sliding in from above the screen to its starting position
+fn initialize_enemies(world: &mut World) {
+    let enemy_frames = enemy_frames();
+    
+    let enemies = [
+        (Point::new(-150, -150), EasingKind::Linear),
+        (Point::new(150, -150), EasingKind::EaseInQuad),
+        (Point::new(-150, 150), EasingKind::EaseOutQuad),
+        (Point::new(150, 150), EasingKind::EaseInOutQuad),
+    ];
+    
+    for &(enemy_position, easing) in &enemies {
+        let entrance = Point::new(enemy_position.x(), -400);
+        world.create_entity()
+            .with(Position(entrance))
+            .with(Tween {
+                from: entrance,
+                to: enemy_position,
+                duration: ENEMY_ENTRANCE_DURATION,
+                elapsed: Duration::default(),
+                easing,
+            })
+            .with(enemy_frames[0].clone())
+            .with(Enemy)
+            .build();
+    }
+}
+
+/// Start a new game: remove every entity (including in-flight projectiles), reset the score and
+/// re-create the initial player and enemies
+fn reset_world(world: &mut World) {
+    world.delete_all();
+    // Actually free the deleted entities before creating the new ones
+    world.maintain();
+    
+    *world.write_resource() = Score(0);
+    *world.write_resource() = Timer::new(LEVEL_TIME);
+    initialize_player(world);
+    initialize_enemies(world);
+}
+
+/// Take the movement for one physics step, from the replay if one is playing and otherwise from
+/// the pending player input, and store it in the InputSnapshot the systems read. The pending
+/// command is used up, so input arriving after this waits for the next step.
+fn capture_input(
+    world: &mut World,
+    pending_command: &mut Option<MovementCommand>,
+    playback: Option<&mut Replay>,
+    step: u32,
+) -> Option<MovementCommand> {
+    let command = match playback {
+        Some(replay) => replay.next_command(step),
+        None => pending_command.take(),
+    };
+    *world.write_resource() = InputSnapshot {movement: command};
+    command
+}
+
+/// Turn the player to face a new direction without moving them
+fn turn_player(world: &mut World, turn: fn(Direction) -> Direction) {
+    let (players, mut velocities): (ReadStorage<Player>, WriteStorage<Velocity>) = world.system_data();
+    for (_, velocity) in (&players, &mut velocities).join() {
+        velocity.turn(turn);
+    }
+}
+
+/// Add the score of the game that just ended to the high score table and save it. Games that
+/// didn't score anything aren't recorded.
+fn record_high_score(world: &World, high_scores: &mut Vec<(String, u32)>) {
+    let score = world.read_resource::<Score>().0;
+    if score == 0 {
+        return;
+    }
+    let name = std::env::var("USER").unwrap_or_else(|_| "player".to_string());
+    high_scores.push((name, score));
+    if let Err(e) = highscores::save_high_scores(high_scores, HIGH_SCORES_FILE) {
+        println!("Could not save high scores: {}", e);
+    }
+    *high_scores = highscores::load_high_scores(HIGH_SCORES_FILE);
+    print_high_scores(high_scores);
+}
+
+fn print_high_scores(high_scores: &[(String, u32)]) {
+    println!("High scores:");
+    for (rank, (name, score)) in high_scores.iter().enumerate() {
+        println!("{:>2}. {:<12} {}", rank + 1, name, score);
+    }
+}
+
+/// Load the saved scene, giving the player back the animation that isn't part of the save file
+fn load_saved_scene(world: &mut World) -> Result<(), String> {
+    scene::load_scene(world, SCENE_FILE)?;
+    
+    let (entities, players, mut animations): (Entities, ReadStorage<Player>, WriteStorage<MovementAnimation>) = world.system_data();
+    for (player, _) in (&entities, &players).join() {
+        animations.insert(player, player_animation()).expect("player from join should be alive");
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), String> {
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
@@ -75,56 +374,86 @@ fn main() -> Result<(), String> {
     
     let mut dispatcher = DispatcherBuilder::new()
         .with(keyboard::Keyboard, "Keyboard", &[])
-        .with(physics::Physics, "Physics", &["Keyboard"])
+        .with(physics::PrevPositionTracker, "PrevPosition", &[])
+        .with(physics::Physics, "Physics", &["Keyboard", "PrevPosition"])
         .with(animator::Animator, "Animator", &["Keyboard"])
+        .with(lifetime::LifetimeSystem, "Lifetime", &[])
+        .with(combat::Combat, "Combat", &["Physics"])
+        .with(tween::TweenSystem, "Tween", &["PrevPosition"])
+        .with(vision::Vision, "Vision", &["Physics", "Tween"])
+        .with(chase::Chase, "Chase", &["Vision"])
+        .with(timer::TimerSystem, "Timer", &[])
         .build();
         
     let mut world = World::new();
     world.register::<KeyboardControlled>();
     world.register::<Position>();
+    world.register::<PrevPosition>();
     world.register::<Velocity>();
     world.register::<Sprite>();
     world.register::<MovementAnimation>();
     world.register::<Player>();
+    world.register::<Lifetime>();
+    world.register::<Projectile>();
+    world.register::<Enemy>();
+    world.register::<Tween>();
+    world.register::<Alert>();
     
     dispatcher.setup(&mut world.res);
     renderer::SystemData::setup(&mut world.res);
     
     // Initialize resource
-    let movement_command: Option<MovementCommand> = None;
-    world.add_resource(movement_command);
+    world.add_resource(InputSnapshot::default());
+    world.add_resource(DeltaTime(PHYSICS_STEP));
+    world.add_resource(Score(0));
+    world.add_resource(Timer::new(LEVEL_TIME));
+    
+    let mut high_scores = highscores::load_high_scores(HIGH_SCORES_FILE);
+    print_high_scores(&high_scores);
+    world.add_resource(Level::from_map(&LEVEL_MAP)?);
     
     let textures = [
         texture_creator.load_texture("assets/bardo.png")?,
+        texture_creator.load_texture("assets/reaper.png")?,
     ];
     
-    // First texture in textures array
-    let player_spritesheet = 0;
-    let player_top_left_frame = Rect::new(0, 0, 26, 36);
-    
-    let player_animation = MovementAnimation {
-        current_frame: 0,
-        up_frames: character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Up),
-        down_frames: character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Down),
-        left_frames: character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Left),
-        right_frames: character_animation_frames(player_spritesheet, player_top_left_frame, Direction::Right),
-    };
+    // Enemies get pixel-perfect hit detection so shots through their transparent edges miss
+    let mut sprite_masks = SpriteMasks::default();
+    let enemy_surface = Surface::from_file("assets/reaper.png")?;
+    for sprite in enemy_frames() {
+        let mask = Mask::from_surface(&enemy_surface, sprite.region)?;
+        sprite_masks.insert(&sprite, mask);
+    }
+    world.add_resource(sprite_masks);
     
-    world.create_entity()
-        .with(KeyboardControlled)
-        .with(Position(Point::new(0, 0)))
-        .with(Velocity {speed: 0, direction: Direction::Right})
-        .with(player_animation.right_frames[0].clone())
-        .with(player_animation)
-        .with(Player) // Add Player component
-        .build();
+    initialize_player(&mut world);
+    initialize_enemies(&mut world);
         
     let mut event_pump = sdl_context.event_pump()?;
+    let mut input = InputHandler::new();
     let mut i = 0;
+    let mut last_frame = Instant::now();
+    // Time that has passed but hasn't been simulated yet
+    let mut accumulator = Duration::default();
+    // Movement input is kept until a physics step consumes it
+    let mut pending_command = None;
+    // Physics steps since the game (re)started, used to line up replays
+    let mut step: u32 = 0;
+    let mut recording = Replay::default();
+    let mut playback: Option<Replay> = None;
+    
+    let mut turn_debouncer = Debouncer::new(TURN_DEBOUNCE);
+    let mut fire_throttle = Throttle::new(FIRE_INTERVAL);
     
     'running: loop {
-        // None - no change, Some(MovementCommand) - perform movement
-        let mut movement_command = None;
+        // Every movement command from this frame's events, resolved into one below
+        let mut movement_commands = Vec::new();
+        let mut fire = false;
+        let mut restart = false;
+        let mut save = false;
+        let mut load = false;
+        let mut save_replay = false;
+        let mut play_replay = false;
         
         // Handle events
         for event in event_pump.poll_iter() {
@@ -133,41 +462,309 @@ fn main() -> Result<(), String> {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     break 'running;
                 },
-                Event::KeyDown { keycode: Some(Keycode::Left), repeat: false, .. } => {
-                    movement_command = Some(MovementCommand::Move(Direction::Left));
+                // Key repeats keep firing while Space is held, throttled to a steady rate
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
+                    if fire_throttle.allow(Instant::now()) {
+                        fire = true;
+                    }
                 },
-                Event::KeyDown { keycode: Some(Keycode::Right), repeat: false, .. } => {
-                    movement_command = Some(MovementCommand::Move(Direction::Right));
+                // Q/E turn the player a quarter turn and X turns them around
+                Event::KeyDown { keycode: Some(Keycode::Q), repeat: false, .. } => {
+                    if turn_debouncer.allow(Instant::now()) {
+                        turn_player(&mut world, Direction::turn_left);
+                    }
                 },
-                Event::KeyDown { keycode: Some(Keycode::Up), repeat: false, .. } => {
-                    movement_command = Some(MovementCommand::Move(Direction::Up));
+                Event::KeyDown { keycode: Some(Keycode::E), repeat: false, .. } => {
+                    if turn_debouncer.allow(Instant::now()) {
+                        turn_player(&mut world, Direction::turn_right);
+                    }
                 },
-                Event::KeyDown { keycode: Some(Keycode::Down), repeat: false, .. } => {
-                    movement_command = Some(MovementCommand::Move(Direction::Down));
+                Event::KeyDown { keycode: Some(Keycode::X), repeat: false, .. } => {
+                    if turn_debouncer.allow(Instant::now()) {
+                        turn_player(&mut world, Direction::opposite);
+                    }
                 },
-                Event::KeyUp { keycode: Some(Keycode::Left), repeat: false, .. } |
-                Event::KeyUp { keycode: Some(Keycode::Right), repeat: false, .. } |
-                Event::KeyUp { keycode: Some(Keycode::Up), repeat: false, .. } |
-                Event::KeyUp { keycode: Some(Keycode::Down), repeat: false, .. } => {
-                    movement_command = Some(MovementCommand::Stop);
+                Event::KeyDown { keycode: Some(Keycode::R), repeat: false, .. } => {
+                    restart = true;
                 },
-                _ => {}
-}
+                Event::KeyDown { keycode: Some(Keycode::F1), repeat: false, .. } => {
+                    print!("{}", inspector::dump_world(&world));
+                },
+                Event::KeyDown { keycode: Some(Keycode::F5), repeat: false, .. } => {
+                    save = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F6), repeat: false, .. } => {
+                    save_replay = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F7), repeat: false, .. } => {
+                    play_replay = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F9), repeat: false, .. } => {
+                    load = true;
+                },
+                event => {
+                    if let Some(command) = translate_event(&event, &mut input) {
+                        movement_commands.push(command);
+                    }
+                },
+            }
         }
         
-        *world.write_resource() = movement_command;
+        if restart {
+            record_high_score(&world, &mut high_scores);
+            reset_world(&mut world);
+            step = 0;
+            recording = Replay::default();
+            playback = None;
+        }
+        // A failed save or a missing/outdated save file shouldn't end the game
+        if save {
+            if let Err(e) = scene::save_scene(&world, SCENE_FILE) {
+                println!("Could not save scene: {}", e);
+            }
+        }
+        if load {
+            if let Err(e) = load_saved_scene(&mut world) {
+                println!("Could not load scene: {}", e);
+            }
+        }
+        if save_replay {
+            if let Err(e) = recording.save(REPLAY_FILE) {
+                println!("Could not save replay: {}", e);
+            }
+        }
+        // Playing a replay restarts the game and takes over movement until it runs out
+        if play_replay {
+            match Replay::load(REPLAY_FILE) {
+                Ok(replay) => {
+                    reset_world(&mut world);
+                    step = 0;
+                    recording = Replay::default();
+                    playback = Some(replay);
+                },
+                Err(e) => println!("Could not load replay: {}", e),
+            }
+        }
         
-        // Update
-        i = (i + 1) % 255;
-        dispatcher.dispatch(&mut world.res);  // Use world.res here
-        world.maintain();
+        // None - no change, Some(MovementCommand) - perform movement
+        let movement_command = resolve_commands(&movement_commands);
+        if movement_command.is_some() && playback.is_none() {
+            pending_command = movement_command;
+        }
+        
+        if fire {
+            fire_from_player(&mut world);
+        }
+        
+        let now = Instant::now();
+        accumulator += now - last_frame;
+        last_frame = now;
+        
+        // Update - run as many fixed steps as needed to catch up with real time
+        let mut steps = 0;
+        while accumulator >= PHYSICS_STEP && steps < MAX_STEPS_PER_FRAME {
+            let command = capture_input(&mut world, &mut pending_command, playback.as_mut(), step);
+            if let Some(command) = command {
+                recording.record(step, command);
+            }
+            if playback.as_ref().is_some_and(Replay::is_finished) {
+                playback = None;
+            }
+            dispatcher.dispatch(&world.res);  // Use world.res here
+            world.maintain();
+            accumulator -= PHYSICS_STEP;
+            steps += 1;
+            step += 1;
+        }
+        // Running out of time restarts the level, just like pressing R
+        if world.read_resource::<Timer>().is_expired() {
+            record_high_score(&world, &mut high_scores);
+            reset_world(&mut world);
+            step = 0;
+            recording = Replay::default();
+            playback = None;
+        }
+        let title = format!("game tutorial - {}", world.read_resource::<Timer>().formatted());
+        canvas.window_mut().set_title(&title).map_err(|e| e.to_string())?;
+        
+        // Any time still left over is lag: the renderer extrapolates up to one step of it and
+        // the rest is dropped
+        accumulator = accumulator.min(PHYSICS_STEP * 2);
         
-        // Render - using the system_data approach correctly
-        renderer::render(&mut canvas, Color::RGB(i, 64, 255 - i), &textures, world.system_data())?;
+        // Render - using the system_data approach correctly, drawing entities part of the way
+        // between their last two physics positions
+        i = (i + 1) % 255;
+        let alpha = accumulator.as_secs_f32() / PHYSICS_STEP.as_secs_f32();
+        renderer::render(&mut canvas, Color::RGB(i, 64, 255 - i), &textures, alpha, world.system_data())?;
         
         // Time management!
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 20));
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / FRAME_RATE));
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world with every component and resource the game sets up, minus anything needing SDL
+    fn game_world() -> World {
+        let mut world = World::new();
+        world.register::<KeyboardControlled>();
+        world.register::<Position>();
+        world.register::<PrevPosition>();
+        world.register::<Velocity>();
+        world.register::<Sprite>();
+        world.register::<MovementAnimation>();
+        world.register::<Player>();
+        world.register::<Lifetime>();
+        world.register::<Projectile>();
+        world.register::<Enemy>();
+        world.register::<Tween>();
+        world.register::<Alert>();
+        world.add_resource(Score(0));
+        world.add_resource(Timer::new(LEVEL_TIME));
+        world
+    }
+
+    fn entity_count(world: &World) -> usize {
+        world.entities().join().count()
+    }
+
+    #[test]
+    fn spawned_projectile_travels_in_the_firing_direction() {
+        let mut world = game_world();
+
+        spawn_projectile(&mut world, Point::new(5, -5), Direction::Left);
+
+        let (projectiles, positions, velocities): (ReadStorage<Projectile>, ReadStorage<Position>, ReadStorage<Velocity>) = world.system_data();
+        let shots: Vec<_> = (&projectiles, &positions, &velocities).join().collect();
+        assert_eq!(shots.len(), 1);
+        let (_, position, velocity) = shots[0];
+        assert_eq!(position.0, Point::new(5, -5));
+        assert_eq!(velocity.direction, Direction::Left);
+        assert_eq!(velocity.secondary_direction, None);
+        assert_eq!(velocity.speed, PROJECTILE_SPEED);
+    }
+
+    #[test]
+    fn reset_world_leaves_only_the_initial_entities() {
+        let mut world = game_world();
+        reset_world(&mut world);
+        let initial = entity_count(&world);
+        assert_eq!(initial, 5); // the player and four enemies
+
+        spawn_projectile(&mut world, Point::new(0, 0), Direction::Up);
+        spawn_projectile(&mut world, Point::new(0, 0), Direction::Down);
+        *world.write_resource() = Score(3);
+        assert_eq!(entity_count(&world), initial + 2);
+
+        reset_world(&mut world);
+        assert_eq!(entity_count(&world), initial);
+        assert_eq!(world.read_storage::<Projectile>().join().count(), 0);
+        assert_eq!(world.read_resource::<Score>().0, 0);
+    }
+
+    fn key_event(keycode: Keycode, pressed: bool, repeat: bool) -> Event {
+        let (timestamp, window_id, scancode, keymod) = (0, 0, None, sdl2::keyboard::Mod::NOMOD);
+        let keycode = Some(keycode);
+        if pressed {
+            Event::KeyDown {timestamp, window_id, keycode, scancode, keymod, repeat}
+        } else {
+            Event::KeyUp {timestamp, window_id, keycode, scancode, keymod, repeat}
+        }
+    }
+
+    #[test]
+    fn key_sequence_translates_into_movement_commands() {
+        use MovementCommand::*;
+        let mut input = InputHandler::new();
+        let sequence = [
+            (Keycode::Right, true, Some(Move(Direction::Right))),
+            (Keycode::Up, true, Some(MoveMultiple(Direction::Up, Direction::Right))),
+            (Keycode::Up, false, Some(Move(Direction::Right))),
+            (Keycode::Right, false, Some(Stop)),
+        ];
+        for (keycode, pressed, expected) in sequence {
+            assert_eq!(translate_event(&key_event(keycode, pressed, false), &mut input), expected,
+                "{:?} {}", keycode, if pressed { "down" } else { "up" });
+        }
+    }
+
+    #[test]
+    fn repeats_and_other_keys_are_not_movement() {
+        let mut input = InputHandler::new();
+        assert_eq!(translate_event(&key_event(Keycode::Left, true, false), &mut input), Some(MovementCommand::Move(Direction::Left)));
+        assert_eq!(translate_event(&key_event(Keycode::Left, true, true), &mut input), None);
+        assert_eq!(translate_event(&key_event(Keycode::A, true, false), &mut input), None);
+        assert_eq!(translate_event(&Event::Quit {timestamp: 0}, &mut input), None);
+    }
+
+    #[test]
+    fn stop_wins_over_moves_in_the_same_frame() {
+        use MovementCommand::*;
+        assert_eq!(resolve_commands(&[Move(Direction::Up), Stop, Move(Direction::Left)]), Some(Stop));
+        // A diagonal beats a straight move, and the latest wins between equals
+        let diagonal = MoveMultiple(Direction::Up, Direction::Left);
+        assert_eq!(resolve_commands(&[diagonal, Move(Direction::Right)]), Some(diagonal));
+        assert_eq!(resolve_commands(&[Move(Direction::Up), Move(Direction::Down)]), Some(Move(Direction::Down)));
+        assert_eq!(resolve_commands(&[]), None);
+    }
+
+    #[test]
+    fn shots_follow_the_sprite_when_moving_diagonally() {
+        let mut world = game_world();
+        reset_world(&mut world);
+        {
+            let (players, mut velocities): (ReadStorage<Player>, WriteStorage<Velocity>) = world.system_data();
+            for (_, velocity) in (&players, &mut velocities).join() {
+                // Up was pressed last, but the sprite faces right
+                *velocity = Velocity {speed: 20, direction: Direction::Up, secondary_direction: Some(Direction::Right)};
+                assert_eq!(animator::facing(velocity), Direction::Right);
+            }
+        }
+
+        fire_from_player(&mut world);
+
+        let (projectiles, velocities): (ReadStorage<Projectile>, ReadStorage<Velocity>) = world.system_data();
+        let directions: Vec<_> = (&projectiles, &velocities).join().map(|(_, velocity)| velocity.direction).collect();
+        assert_eq!(directions, [Direction::Right]);
+    }
+
+    #[test]
+    fn input_arriving_after_the_snapshot_waits_for_the_next_step() {
+        let mut world = game_world();
+        world.add_resource(InputSnapshot::default());
+        reset_world(&mut world);
+        let player_velocity = |world: &World| {
+            let (players, velocities): (ReadStorage<Player>, ReadStorage<Velocity>) = world.system_data();
+            (&players, &velocities).join().map(|(_, velocity)| (velocity.speed, velocity.offset())).next()
+        };
+
+        let mut pending_command = Some(MovementCommand::Move(Direction::Left));
+        assert_eq!(capture_input(&mut world, &mut pending_command, None, 0), Some(MovementCommand::Move(Direction::Left)));
+        assert_eq!(pending_command, None);
+        // A key event handled after the step's snapshot was taken
+        pending_command = Some(MovementCommand::Stop);
+        keyboard::Keyboard.run_now(&world.res);
+        assert_eq!(player_velocity(&world), Some((20, (-1, 0))));
+
+        capture_input(&mut world, &mut pending_command, None, 1);
+        keyboard::Keyboard.run_now(&world.res);
+        assert_eq!(player_velocity(&world).map(|(speed, _)| speed), Some(0));
+    }
+
+    #[test]
+    fn a_playing_replay_leaves_pending_input_alone() {
+        let mut world = game_world();
+        world.add_resource(InputSnapshot::default());
+        let mut replay = Replay::default();
+        replay.record(3, MovementCommand::Move(Direction::Up));
+        let mut pending_command = Some(MovementCommand::Stop);
+
+        assert_eq!(capture_input(&mut world, &mut pending_command, Some(&mut replay), 3), Some(MovementCommand::Move(Direction::Up)));
+        assert_eq!(world.read_resource::<InputSnapshot>().movement, Some(MovementCommand::Move(Direction::Up)));
+        assert_eq!(pending_command, Some(MovementCommand::Stop));
+    }
+}
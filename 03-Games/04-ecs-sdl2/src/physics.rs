@@ -1,18 +1,73 @@
 use specs::prelude::*;
 
+use sdl2::rect::Point;
+
 use crate::components::*;
+use crate::level::Level;
+use super::PHYSICS_STEP;
 
 pub struct Physics;
 
+/// Predicts where an entity will be `dt` seconds after it was at `pos`, assuming it keeps moving
+/// with the same velocity. Velocities are in pixels per physics step.
+pub fn extrapolate(pos: Point, vel: &Velocity, dt: f32) -> Point {
+    let steps = dt / PHYSICS_STEP.as_secs_f32();
+    let (dx, dy) = vel.offset();
+    let distance = vel.speed as f32 * steps;
+    pos.offset((dx as f32 * distance).round() as i32, (dy as f32 * distance).round() as i32)
+}
+
+/// Remembers where every entity was before the next physics step so the renderer can interpolate
+pub struct PrevPositionTracker;
+
+impl<'a> System<'a> for PrevPositionTracker {
+    type SystemData = (Entities<'a>, ReadStorage<'a, Position>, WriteStorage<'a, PrevPosition>);
+
+    fn run(&mut self, (entities, positions, mut prev_positions): Self::SystemData) {
+        for (entity, position) in (&entities, &positions).join() {
+            prev_positions.insert(entity, PrevPosition(position.0)).expect("entity from join should be alive");
+        }
+    }
+}
+
 impl<'a> System<'a> for Physics {
-    type SystemData = (WriteStorage<'a, Position>, ReadStorage<'a, Velocity>);
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Projectile>,
+        ReadExpect<'a, Level>,
+    );
 
-    fn run(&mut self, (mut positions, velocities): Self::SystemData) {
-        for (position, velocity) in (&mut positions, &velocities).join() {
+    fn run(&mut self, (entities, mut positions, velocities, projectiles, level): Self::SystemData) {
+        for (entity, position, velocity) in (&entities, &mut positions, &velocities).join() {
             if velocity.speed > 0 {
-                let (dx, dy) = velocity.direction.to_offset();
-                position.0 = position.0.offset(dx * velocity.speed, dy * velocity.speed);
+                let (dx, dy) = velocity.offset();
+                let next = position.0.offset(dx * velocity.speed, dy * velocity.speed);
+                if !level.is_wall(next) {
+                    position.0 = next;
+                } else if projectiles.contains(entity) {
+                    // Shots are stopped by walls, everything else just can't walk through them
+                    entities.delete(entity).expect("entity from join should be alive");
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extrapolation_follows_the_velocity() {
+        let step = PHYSICS_STEP.as_secs_f32();
+        let moving = Velocity {speed: 20, direction: Direction::Right, secondary_direction: Some(Direction::Up)};
+        assert_eq!(extrapolate(Point::new(100, 100), &moving, step), Point::new(120, 80));
+        assert_eq!(extrapolate(Point::new(100, 100), &moving, step / 2.0), Point::new(110, 90));
+        assert_eq!(extrapolate(Point::new(100, 100), &moving, 0.0), Point::new(100, 100));
+
+        let stopped = Velocity {speed: 0, direction: Direction::Left, secondary_direction: None};
+        assert_eq!(extrapolate(Point::new(5, 5), &stopped, step), Point::new(5, 5));
+    }
+}
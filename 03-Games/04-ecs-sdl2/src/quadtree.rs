@@ -0,0 +1,158 @@
+use sdl2::rect::Rect;
+use specs::Entity;
+
+/// How many items a node holds before it splits into four children
+const DEFAULT_CAPACITY: usize = 4;
+/// Nodes this deep never split, so many overlapping items can't recurse forever
+const MAX_DEPTH: u32 = 8;
+
+/// Spatial index over entity bounding boxes. Each node splits into four quadrants once it holds
+/// more than `capacity` items, so densely clustered entities end up in small nodes and queries
+/// only look at the nodes that overlap the queried region.
+#[derive(Debug)]
+pub struct QuadTree {
+    bounds: Rect,
+    capacity: usize,
+    depth: u32,
+    // Items that don't fit entirely inside a single child stay in this node
+    items: Vec<(Entity, Rect)>,
+    children: Option<Box<[QuadTree; 4]>>,
+}
+
+impl QuadTree {
+    pub fn new(bounds: Rect) -> Self {
+        Self::with_capacity(bounds, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(bounds: Rect, capacity: usize) -> Self {
+        Self::node(bounds, capacity.max(1), 0)
+    }
+
+    fn node(bounds: Rect, capacity: usize, depth: u32) -> Self {
+        Self {
+            bounds,
+            capacity,
+            depth,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    /// Adds an entity with the given bounding box. Boxes that fall outside the bounds of the
+    /// tree are still stored (in the root) so they can't be lost.
+    pub fn insert(&mut self, entity: Entity, rect: Rect) {
+        if let Some(children) = &mut self.children {
+            if let Some(child) = children.iter_mut().find(|child| contains(child.bounds, rect)) {
+                child.insert(entity, rect);
+                return;
+            }
+        }
+
+        self.items.push((entity, rect));
+        if self.children.is_none() && self.items.len() > self.capacity && self.depth < MAX_DEPTH {
+            self.split();
+        }
+    }
+
+    /// Returns every entity whose bounding box intersects the given region
+    pub fn query(&self, region: Rect) -> Vec<Entity> {
+        let mut found = Vec::new();
+        self.query_into(region, &mut found);
+        found
+    }
+
+    fn query_into(&self, region: Rect, found: &mut Vec<Entity>) {
+        found.extend(
+            self.items.iter()
+                .filter(|(_, rect)| rect.has_intersection(region))
+                .map(|&(entity, _)| entity)
+        );
+
+        if let Some(children) = &self.children {
+            for child in children.iter().filter(|child| child.bounds.has_intersection(region)) {
+                child.query_into(region, found);
+            }
+        }
+    }
+
+    fn split(&mut self) {
+        let (x, y) = (self.bounds.x(), self.bounds.y());
+        let (width, height) = self.bounds.size();
+        let (left_width, top_height) = (width / 2, height / 2);
+        // Rect can't have a zero size, so stop splitting once nodes get too small
+        if left_width == 0 || top_height == 0 {
+            return;
+        }
+        let (right_width, bottom_height) = (width - left_width, height - top_height);
+        let (mid_x, mid_y) = (x + left_width as i32, y + top_height as i32);
+
+        let depth = self.depth + 1;
+        self.children = Some(Box::new([
+            QuadTree::node(Rect::new(x, y, left_width, top_height), self.capacity, depth),
+            QuadTree::node(Rect::new(mid_x, y, right_width, top_height), self.capacity, depth),
+            QuadTree::node(Rect::new(x, mid_y, left_width, bottom_height), self.capacity, depth),
+            QuadTree::node(Rect::new(mid_x, mid_y, right_width, bottom_height), self.capacity, depth),
+        ]));
+
+        // Push down everything that now fits entirely inside one of the children
+        for (entity, rect) in std::mem::take(&mut self.items) {
+            self.insert(entity, rect);
+        }
+    }
+}
+
+/// Whether `inner` lies entirely within `outer`
+fn contains(outer: Rect, inner: Rect) -> bool {
+    inner.left() >= outer.left() && inner.right() <= outer.right()
+        && inner.top() >= outer.top() && inner.bottom() <= outer.bottom()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World};
+    use std::collections::HashSet;
+
+    /// A 10x10 grid of 5x5 boxes, 20 pixels apart, plus a cluster of small boxes in one corner
+    fn populated_tree(world: &mut World) -> (QuadTree, Vec<(Entity, Rect)>) {
+        let mut items = Vec::new();
+        for row in 0..10 {
+            for col in 0..10 {
+                items.push((world.create_entity().build(), Rect::new(col * 20, row * 20, 5, 5)));
+            }
+        }
+        for i in 0..20 {
+            items.push((world.create_entity().build(), Rect::new(1 + i % 4, 1 + i / 4, 2, 2)));
+        }
+
+        let mut tree = QuadTree::with_capacity(Rect::new(0, 0, 200, 200), 2);
+        for &(entity, rect) in &items {
+            tree.insert(entity, rect);
+        }
+        (tree, items)
+    }
+
+    #[test]
+    fn query_returns_exactly_the_intersecting_entities() {
+        let mut world = World::new();
+        let (tree, items) = populated_tree(&mut world);
+
+        for region in [Rect::new(30, 30, 50, 50), Rect::new(0, 0, 4, 4), Rect::new(0, 0, 200, 200), Rect::new(6, 6, 10, 10)] {
+            let found_list = tree.query(region);
+            let found: HashSet<Entity> = found_list.iter().copied().collect();
+            assert_eq!(found.len(), found_list.len(), "no entity is returned twice");
+            let expected: HashSet<Entity> = items.iter()
+                .filter(|(_, rect)| rect.has_intersection(region))
+                .map(|&(entity, _)| entity)
+                .collect();
+            assert_eq!(found, expected, "query {:?}", region);
+        }
+    }
+
+    #[test]
+    fn crowded_nodes_split() {
+        let mut world = World::new();
+        let (tree, _) = populated_tree(&mut world);
+        assert!(tree.children.is_some());
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+/// Lets a trigger through only once it has been quiet for `min_interval`. Every call, allowed or
+/// not, restarts the wait, so a burst of rapid triggers only lets the first one through.
+pub struct Debouncer {
+    pub min_interval: Duration,
+    pub last: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(min_interval: Duration) -> Self {
+        Debouncer {min_interval, last: None}
+    }
+
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let allowed = match self.last {
+            Some(last) => now.saturating_duration_since(last) >= self.min_interval,
+            None => true,
+        };
+        self.last = Some(now);
+        allowed
+    }
+}
+
+/// Lets at most one action through per `interval`, starting with the very first. Unlike a
+/// `Debouncer`, blocked calls don't push the next allowed time back, so holding a trigger down
+/// still fires at a steady rate.
+pub struct Throttle {
+    pub interval: Duration,
+    last_allowed: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Throttle {interval, last_allowed: None}
+    }
+
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let allowed = match self.last_allowed {
+            Some(last) => now.saturating_duration_since(last) >= self.interval,
+            None => true,
+        };
+        if allowed {
+            self.last_allowed = Some(now);
+        }
+        allowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn debouncer_allows_again_only_after_a_quiet_interval() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(INTERVAL);
+        assert!(debouncer.allow(start));
+        assert!(!debouncer.allow(start + ms(50)));
+        // Still within the interval of the blocked call above
+        assert!(!debouncer.allow(start + ms(120)));
+        assert!(debouncer.allow(start + ms(220)));
+    }
+
+    #[test]
+    fn throttle_fires_immediately_then_blocks_until_the_interval_passes() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(INTERVAL);
+        assert!(throttle.allow(start));
+        assert!(!throttle.allow(start + ms(50)));
+        assert!(!throttle.allow(start + ms(99)));
+        // A debouncer would still be blocked here, since the last call was only 1ms ago
+        assert!(throttle.allow(start + ms(100)));
+        assert!(!throttle.allow(start + ms(150)));
+        assert!(throttle.allow(start + ms(200)));
+    }
+
+    #[test]
+    fn holding_a_trigger_fires_a_throttle_but_not_a_debouncer() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(INTERVAL);
+        let mut debouncer = Debouncer::new(INTERVAL);
+        // A call every 20ms for half a second
+        let calls = (0..25).map(|i| start + ms(i * 20));
+        let (mut fired, mut debounced) = (0, 0);
+        for now in calls {
+            fired += u32::from(throttle.allow(now));
+            debounced += u32::from(debouncer.allow(now));
+        }
+        assert_eq!((fired, debounced), (5, 1));
+    }
+}
@@ -4,20 +4,60 @@ use sdl2::render::{WindowCanvas, Texture};
 use specs::prelude::*;
 
 use crate::components::*;
+use crate::hud;
+use crate::level::{Level, Tile};
+use crate::physics::extrapolate;
+use crate::tween::lerp_point;
+use super::{Score, PHYSICS_STEP};
 
 // Type alias for the data needed by the renderer
 pub type SystemData<'a> = (
+    Entities<'a>,
     ReadStorage<'a, Position>,
+    ReadStorage<'a, PrevPosition>,
+    ReadStorage<'a, Velocity>,
     ReadStorage<'a, Sprite>,
+    ReadStorage<'a, Projectile>,
+    ReadStorage<'a, Alert>,
+    ReadExpect<'a, Score>,
+    ReadExpect<'a, Level>,
 );
 
+const PROJECTILE_SIZE: u32 = 6;
+const WALL_COLOR: Color = Color::RGB(90, 90, 90);
+const MUD_COLOR: Color = Color::RGB(100, 70, 40);
+const ALERT_COLOR: Color = Color::RGB(255, 60, 60);
+const ALERT_SIZE: u32 = 8;
+
+/// Where to draw an entity that has moved from `prev` to `current` in the last physics step, given
+/// how far (0.0 to 1.0) we are into the next step
+pub fn interpolate_position(prev: Point, current: Point, alpha: f32) -> Point {
+    lerp_point(prev, current, alpha.clamp(0.0, 1.0))
+}
+
 pub fn render(
     canvas: &mut WindowCanvas,
     background: Color,
     textures: &[Texture],
+    alpha: f32,
     data: SystemData,
 ) -> Result<(), String> {
-    let (positions, sprites) = data;
+    let (entities, positions, prev_positions, velocities, sprites, projectiles, alerts, score, level) = data;
+    let draw_position = |entity: Entity, pos: &Position| {
+        if alpha > 1.0 {
+            // Physics has fallen behind, so guess where moving entities are by now
+            match velocities.get(entity) {
+                Some(vel) => extrapolate(pos.0, vel, (alpha - 1.0) * PHYSICS_STEP.as_secs_f32()),
+                None => pos.0,
+            }
+        } else {
+            // Entities that haven't been through a physics step yet are drawn where they are
+            match prev_positions.get(entity) {
+                Some(prev) => interpolate_position(prev.0, pos.0, alpha),
+                None => pos.0,
+            }
+        }
+    };
 
     canvas.set_draw_color(background);
     canvas.clear();
@@ -25,12 +65,21 @@ pub fn render(
     // Get the dimensions of the window
     let (width, height) = canvas.output_size()?;
 
+    // The level never moves, so it is drawn straight from its tiles under everything else
+    let screen_center = Point::new(width as i32 / 2, height as i32 / 2);
+    for (kind, color) in [(Tile::Mud, MUD_COLOR), (Tile::Wall, WALL_COLOR)] {
+        canvas.set_draw_color(color);
+        for tile in level.tiles_of(kind) {
+            canvas.fill_rect(Rect::new(tile.x() + screen_center.x(), tile.y() + screen_center.y(), tile.width(), tile.height()))?;
+        }
+    }
+
     // Draw all entities with Position and Sprite components
-    for (pos, sprite) in (&positions, &sprites).join() {
+    for (entity, pos, sprite) in (&entities, &positions, &sprites).join() {
         let current_frame = sprite.region;
         
         // Treat the center of the screen as the (0, 0) coordinate
-        let screen_position = pos.0 + Point::new(width as i32 / 2, height as i32 / 2);
+        let screen_position = draw_position(entity, pos) + Point::new(width as i32 / 2, height as i32 / 2);
         
         // Create a rectangle centered on the screen position
         let screen_rect = Rect::from_center(
@@ -46,7 +95,42 @@ pub fn render(
         )?;
     }
 
+    // Projectiles have no spritesheet, so draw them as small squares
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    for (entity, pos, _) in (&entities, &positions, &projectiles).join() {
+        let screen_position = draw_position(entity, pos) + Point::new(width as i32 / 2, height as i32 / 2);
+        canvas.fill_rect(Rect::from_center(screen_position, PROJECTILE_SIZE, PROJECTILE_SIZE))?;
+    }
+
+    // Enemies that can see the player get a marker above their heads
+    canvas.set_draw_color(ALERT_COLOR);
+    for (entity, pos, sprite, _) in (&entities, &positions, &sprites, &alerts).join() {
+        let screen_position = draw_position(entity, pos) + Point::new(width as i32 / 2, height as i32 / 2);
+        let above_head = screen_position.offset(0, -(sprite.region.height() as i32 / 2) - ALERT_SIZE as i32);
+        canvas.fill_rect(Rect::from_center(above_head, ALERT_SIZE, ALERT_SIZE))?;
+    }
+
+    // Draw the score in the top-left corner
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.fill_rects(&hud::number_segments(score.0, Point::new(10, 10)))?;
+
     canvas.present();
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation_runs_from_the_previous_to_the_current_position() {
+        let (prev, current) = (Point::new(10, -20), Point::new(30, 0));
+        assert_eq!(interpolate_position(prev, current, 0.0), prev);
+        assert_eq!(interpolate_position(prev, current, 1.0), current);
+        assert_eq!(interpolate_position(prev, current, 0.5), Point::new(20, -10));
+        // Out of range alphas stay between the two positions
+        assert_eq!(interpolate_position(prev, current, -0.5), prev);
+        assert_eq!(interpolate_position(prev, current, 1.5), current);
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use crate::MovementCommand;
+use crate::components::Direction;
+
+// Replay files start with these bytes. Bump the version whenever the format changes so that old
+// files are rejected instead of being misread.
+const REPLAY_MAGIC: &[u8] = b"ECSR";
+const REPLAY_VERSION: u8 = 1;
+
+const STOP: u8 = 0;
+const MOVE: u8 = 1;
+const MOVE_MULTIPLE: u8 = 2;
+
+/// The movement commands given during a run, tagged with the physics step they were applied on.
+///
+/// Only movement is recorded, so a replay reproduces where the player walked but not their shots.
+#[derive(Debug, Default)]
+pub struct Replay {
+    commands: VecDeque<(u32, MovementCommand)>,
+}
+
+impl Replay {
+    pub fn record(&mut self, step: u32, command: MovementCommand) {
+        self.commands.push_back((step, command));
+    }
+
+    /// Returns the command recorded for the given step, if any. Steps must be asked for in order.
+    pub fn next_command(&mut self, step: u32) -> Option<MovementCommand> {
+        match self.commands.front() {
+            Some(&(recorded_step, _)) if recorded_step <= step => {
+                self.commands.pop_front().map(|(_, command)| command)
+            },
+            _ => None,
+        }
+    }
+
+    /// True once every recorded command has been played back
+    pub fn is_finished(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Write the replay to the file at `path`.
+    ///
+    /// After the header, each command takes a little-endian u32 step, a tag byte and then one
+    /// byte per direction (see `Direction::to_byte`).
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut out = REPLAY_MAGIC.to_vec();
+        out.push(REPLAY_VERSION);
+        for &(step, command) in &self.commands {
+            out.extend_from_slice(&step.to_le_bytes());
+            match command {
+                MovementCommand::Stop => out.push(STOP),
+                MovementCommand::Move(direction) => out.extend_from_slice(&[MOVE, direction.to_byte()]),
+                MovementCommand::MoveMultiple(direction, secondary_direction) => {
+                    out.extend_from_slice(&[MOVE_MULTIPLE, direction.to_byte(), secondary_direction.to_byte()]);
+                },
+            }
+        }
+        fs::write(path, out).map_err(|e| format!("Could not write replay to {}: {}", path, e))
+    }
+
+    /// Read a replay previously written by `save`
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Could not read replay from {}: {}", path, e))?;
+        let body = bytes.strip_prefix(REPLAY_MAGIC)
+            .ok_or_else(|| format!("{} is not a replay file", path))?;
+        let (&version, mut body) = body.split_first()
+            .ok_or_else(|| format!("{} is not a replay file", path))?;
+        if version != REPLAY_VERSION {
+            return Err(format!("Unsupported replay version {} (expected {})", version, REPLAY_VERSION));
+        }
+
+        let truncated = || format!("{} is truncated", path);
+        let mut replay = Replay::default();
+        while !body.is_empty() {
+            let (step, rest) = body.split_at_checked(4).ok_or_else(truncated)?;
+            let step = u32::from_le_bytes(step.try_into().expect("split at 4 bytes"));
+            let (&tag, rest) = rest.split_first().ok_or_else(truncated)?;
+            let (command, rest) = match tag {
+                STOP => (MovementCommand::Stop, rest),
+                MOVE => {
+                    let (&direction, rest) = rest.split_first().ok_or_else(truncated)?;
+                    (MovementCommand::Move(read_direction(direction)?), rest)
+                },
+                MOVE_MULTIPLE => match *rest {
+                    [direction, secondary_direction, ref rest @ ..] => {
+                        let command = MovementCommand::MoveMultiple(read_direction(direction)?, read_direction(secondary_direction)?);
+                        (command, rest)
+                    },
+                    _ => return Err(truncated()),
+                },
+                _ => return Err(format!("Unknown replay command {}", tag)),
+            };
+            replay.record(step, command);
+            body = rest;
+        }
+        Ok(replay)
+    }
+}
+
+fn read_direction(b: u8) -> Result<Direction, String> {
+    Direction::from_byte(b).ok_or_else(|| format!("Invalid direction byte {}", b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Direction::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn replay_round_trips_through_a_file() {
+        let path = temp_path("ecs_replay_round_trip.bin");
+        let mut replay = Replay::default();
+        replay.record(0, MovementCommand::Move(Up));
+        replay.record(3, MovementCommand::MoveMultiple(Left, Down));
+        replay.record(300, MovementCommand::Stop);
+        replay.save(&path).unwrap();
+        let mut loaded = Replay::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded.next_command(0), Some(MovementCommand::Move(Up)));
+        assert_eq!(loaded.next_command(1), None);
+        assert_eq!(loaded.next_command(3), Some(MovementCommand::MoveMultiple(Left, Down)));
+        assert_eq!(loaded.next_command(300), Some(MovementCommand::Stop));
+        assert!(loaded.is_finished());
+    }
+
+    #[test]
+    fn out_of_range_direction_bytes_are_rejected() {
+        let path = temp_path("ecs_replay_bad_direction.bin");
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&[MOVE, 4]);
+        fs::write(&path, bytes).unwrap();
+        let result = Replay::load(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(result.err(), Some("Invalid direction byte 4".to_string()));
+    }
+}
@@ -0,0 +1,283 @@
+use sdl2::rect::{Point, Rect};
+use specs::prelude::*;
+use std::fs;
+use std::time::Duration;
+
+use crate::components::*;
+
+// Scene files start with this header. Bump the version whenever the format changes so that old
+// files are rejected instead of being misread.
+const SCENE_HEADER: &str = "ecs-scene";
+const SCENE_VERSION: u32 = 1;
+
+/// Everything saved about a single entity
+#[derive(Debug, Default)]
+struct EntityRecord {
+    position: Option<Point>,
+    velocity: Option<(i32, Direction, Option<Direction>)>,
+    sprite: Option<(usize, Rect)>,
+    lifetime: Option<Duration>,
+    tags: Vec<String>,
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+/// Write every entity in the world, with its standard components, to the file at `path`.
+///
+/// The format is line based: a version header followed by one block per entity. Each block starts
+/// with an `entity` line and has one line per component, e.g.
+///
+/// ```text
+/// ecs-scene 1
+/// entity
+/// position 0 0
+/// velocity 0 right up
+/// sprite 0 0 72 26 36
+/// tag player
+/// ```
+pub fn save_scene(world: &World, path: &str) -> Result<(), String> {
+    let entities = world.entities();
+    let positions = world.read_storage::<Position>();
+    let velocities = world.read_storage::<Velocity>();
+    let sprites = world.read_storage::<Sprite>();
+    let lifetimes = world.read_storage::<Lifetime>();
+    let keyboard_controlled = world.read_storage::<KeyboardControlled>();
+    let players = world.read_storage::<Player>();
+    let enemies = world.read_storage::<Enemy>();
+    let projectiles = world.read_storage::<Projectile>();
+
+    let mut out = format!("{} {}\n", SCENE_HEADER, SCENE_VERSION);
+    for entity in (&entities).join() {
+        out.push_str("entity\n");
+        if let Some(position) = positions.get(entity) {
+            out.push_str(&format!("position {} {}\n", position.0.x(), position.0.y()));
+        }
+        if let Some(velocity) = velocities.get(entity) {
+            out.push_str(&format!("velocity {} {}", velocity.speed, direction_name(velocity.direction)));
+            if let Some(secondary_direction) = velocity.secondary_direction {
+                out.push_str(&format!(" {}", direction_name(secondary_direction)));
+            }
+            out.push('\n');
+        }
+        if let Some(sprite) = sprites.get(entity) {
+            let region = sprite.region;
+            out.push_str(&format!("sprite {} {} {} {} {}\n",
+                sprite.spritesheet, region.x(), region.y(), region.width(), region.height()));
+        }
+        if let Some(lifetime) = lifetimes.get(entity) {
+            out.push_str(&format!("lifetime {}\n", lifetime.remaining.as_millis()));
+        }
+        if keyboard_controlled.contains(entity) {
+            out.push_str("tag keyboard_controlled\n");
+        }
+        if players.contains(entity) {
+            out.push_str("tag player\n");
+        }
+        if enemies.contains(entity) {
+            out.push_str("tag enemy\n");
+        }
+        if projectiles.contains(entity) {
+            out.push_str("tag projectile\n");
+        }
+    }
+
+    fs::write(path, out).map_err(|e| format!("Could not write scene to {}: {}", path, e))
+}
+
+/// Replace every entity in the world with the ones saved in the file at `path`.
+///
+/// The whole file is parsed before anything is changed, so the world is left untouched if the
+/// file is missing, malformed or from a different version. Components that aren't saved (e.g.
+/// animations) need to be re-attached by the caller.
+pub fn load_scene(world: &mut World, path: &str) -> Result<(), String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read scene from {}: {}", path, e))?;
+    let records = parse_scene(&contents)?;
+
+    world.delete_all();
+    world.maintain();
+
+    for record in records {
+        let mut builder = world.create_entity();
+        if let Some(position) = record.position {
+            builder = builder.with(Position(position));
+        }
+        if let Some((speed, direction, secondary_direction)) = record.velocity {
+            builder = builder.with(Velocity {speed, direction, secondary_direction});
+        }
+        if let Some((spritesheet, region)) = record.sprite {
+            builder = builder.with(Sprite {spritesheet, region});
+        }
+        if let Some(remaining) = record.lifetime {
+            builder = builder.with(Lifetime {remaining});
+        }
+        for tag in &record.tags {
+            builder = match tag.as_str() {
+                "keyboard_controlled" => builder.with(KeyboardControlled),
+                "player" => builder.with(Player),
+                "enemy" => builder.with(Enemy),
+                "projectile" => builder.with(Projectile),
+                _ => unreachable!("tags are validated while parsing"),
+            };
+        }
+        builder.build();
+    }
+
+    Ok(())
+}
+
+fn parse_scene(contents: &str) -> Result<Vec<EntityRecord>, String> {
+    let mut lines = contents.lines().enumerate();
+
+    let header = lines.next().map(|(_, line)| line).unwrap_or("");
+    let version = match header.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [SCENE_HEADER, version] => version.parse::<u32>()
+            .map_err(|_| format!("Invalid scene version: {}", version))?,
+        _ => return Err("Missing scene header".to_string()),
+    };
+    if version != SCENE_VERSION {
+        return Err(format!("Unsupported scene version {} (expected {})", version, SCENE_VERSION));
+    }
+
+    let mut records: Vec<EntityRecord> = Vec::new();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields[0] == "entity" {
+            records.push(EntityRecord::default());
+            continue;
+        }
+
+        let record = records.last_mut()
+            .ok_or_else(|| format!("Line {}: component before the first entity", line_number))?;
+        let invalid = || format!("Line {}: invalid component '{}'", line_number, line);
+        let int = |field: &str| field.parse::<i32>().map_err(|_| invalid());
+
+        match fields.as_slice() {
+            ["position", x, y] => record.position = Some(Point::new(int(x)?, int(y)?)),
+            ["velocity", speed, direction] => {
+                let direction = Direction::from_str(direction).ok_or_else(invalid)?;
+                record.velocity = Some((int(speed)?, direction, None));
+            },
+            ["velocity", speed, direction, secondary_direction] => {
+                let direction = Direction::from_str(direction).ok_or_else(invalid)?;
+                let secondary_direction = Direction::from_str(secondary_direction).ok_or_else(invalid)?;
+                record.velocity = Some((int(speed)?, direction, Some(secondary_direction)));
+            },
+            ["sprite", spritesheet, x, y, width, height] => {
+                let spritesheet = spritesheet.parse::<usize>().map_err(|_| invalid())?;
+                let width = width.parse::<u32>().map_err(|_| invalid())?;
+                let height = height.parse::<u32>().map_err(|_| invalid())?;
+                record.sprite = Some((spritesheet, Rect::new(int(x)?, int(y)?, width, height)));
+            },
+            ["lifetime", millis] => {
+                let millis = millis.parse::<u64>().map_err(|_| invalid())?;
+                record.lifetime = Some(Duration::from_millis(millis));
+            },
+            ["tag", tag @ ("keyboard_controlled" | "player" | "enemy" | "projectile")] => {
+                record.tags.push(tag.to_string());
+            },
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene_world() -> World {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.register::<Sprite>();
+        world.register::<Lifetime>();
+        world.register::<KeyboardControlled>();
+        world.register::<Player>();
+        world.register::<Enemy>();
+        world.register::<Projectile>();
+        world
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn two_entity_scene_round_trips() {
+        let path = temp_path("ecs_scene_round_trip.txt");
+        let mut world = scene_world();
+        world.create_entity()
+            .with(Position(Point::new(-3, 7)))
+            .with(Velocity {speed: 20, direction: Direction::Right, secondary_direction: Some(Direction::Up)})
+            .with(Sprite {spritesheet: 0, region: Rect::new(0, 72, 26, 36)})
+            .with(KeyboardControlled)
+            .with(Player)
+            .build();
+        world.create_entity()
+            .with(Position(Point::new(150, 150)))
+            .with(Lifetime {remaining: Duration::from_millis(250)})
+            .with(Enemy)
+            .build();
+        save_scene(&world, &path).unwrap();
+
+        let mut loaded = scene_world();
+        // Anything already in the world is replaced
+        loaded.create_entity().with(Projectile).build();
+        load_scene(&mut loaded, &path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let (entities, positions, velocities, sprites, lifetimes) = (
+            loaded.entities(),
+            loaded.read_storage::<Position>(),
+            loaded.read_storage::<Velocity>(),
+            loaded.read_storage::<Sprite>(),
+            loaded.read_storage::<Lifetime>(),
+        );
+        assert_eq!((&entities).join().count(), 2);
+        assert_eq!(loaded.read_storage::<Projectile>().join().count(), 0);
+
+        let (player, _, _) = (&entities, &loaded.read_storage::<Player>(), &loaded.read_storage::<KeyboardControlled>()).join().next().unwrap();
+        assert_eq!(positions.get(player).unwrap().0, Point::new(-3, 7));
+        let velocity = velocities.get(player).unwrap();
+        assert_eq!((velocity.speed, velocity.direction, velocity.secondary_direction), (20, Direction::Right, Some(Direction::Up)));
+        assert_eq!(sprites.get(player).unwrap().region, Rect::new(0, 72, 26, 36));
+
+        let (enemy, _) = (&entities, &loaded.read_storage::<Enemy>()).join().next().unwrap();
+        assert_eq!(positions.get(enemy).unwrap().0, Point::new(150, 150));
+        assert_eq!(lifetimes.get(enemy).unwrap().remaining, Duration::from_millis(250));
+        assert!(velocities.get(enemy).is_none());
+    }
+
+    #[test]
+    fn other_versions_are_rejected_without_touching_the_world() {
+        let path = temp_path("ecs_scene_version.txt");
+        fs::write(&path, "ecs-scene 2\nentity\nposition 0 0\n").unwrap();
+
+        let mut world = scene_world();
+        world.create_entity().with(Player).build();
+        let result = load_scene(&mut world, &path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(result, Err("Unsupported scene version 2 (expected 1)".to_string()));
+        assert_eq!(world.read_storage::<Player>().join().count(), 1);
+    }
+
+    #[test]
+    fn malformed_lines_report_their_line_number() {
+        let err = parse_scene("ecs-scene 1\nentity\nposition 0 zero\n").unwrap_err();
+        assert!(err.starts_with("Line 3:"), "{}", err);
+    }
+}
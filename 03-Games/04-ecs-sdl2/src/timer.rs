@@ -0,0 +1,76 @@
+use specs::prelude::*;
+use std::time::Duration;
+
+use super::DeltaTime;
+
+/// Counts down the time left to finish the level
+pub struct Timer {
+    pub remaining: Duration,
+}
+
+impl Timer {
+    pub fn new(remaining: Duration) -> Self {
+        Timer {remaining}
+    }
+
+    /// Count down by `dt`, stopping at zero
+    pub fn tick(&mut self, dt: Duration) {
+        self.remaining = self.remaining.saturating_sub(dt);
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining == Duration::ZERO
+    }
+
+    /// The time left as "M:SS", rounding partial seconds up so "0:00" only shows once expired
+    pub fn formatted(&self) -> String {
+        let secs = self.remaining.as_secs() + u64::from(self.remaining.subsec_nanos() > 0);
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+pub struct TimerSystem;
+
+impl<'a> System<'a> for TimerSystem {
+    type SystemData = (
+        ReadExpect<'a, DeltaTime>,
+        WriteExpect<'a, Timer>,
+    );
+
+    fn run(&mut self, (delta_time, mut timer): Self::SystemData) {
+        timer.tick(delta_time.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticking_past_zero_clamps_to_expired() {
+        let mut timer = Timer::new(Duration::from_secs(2));
+        timer.tick(Duration::from_millis(1500));
+        assert!(!timer.is_expired());
+        timer.tick(Duration::from_secs(1));
+        assert!(timer.is_expired());
+        assert_eq!(timer.remaining, Duration::ZERO);
+        assert_eq!(timer.formatted(), "0:00");
+    }
+
+    #[test]
+    fn formats_as_minutes_and_seconds() {
+        assert_eq!(Timer::new(Duration::from_secs(65)).formatted(), "1:05");
+        assert_eq!(Timer::new(Duration::from_secs(600)).formatted(), "10:00");
+        // Partial seconds round up
+        assert_eq!(Timer::new(Duration::from_millis(59_001)).formatted(), "1:00");
+    }
+
+    #[test]
+    fn system_counts_down_by_the_frame_time() {
+        let mut world = World::new();
+        world.add_resource(DeltaTime(Duration::from_millis(250)));
+        world.add_resource(Timer::new(Duration::from_secs(1)));
+        TimerSystem.run_now(&world.res);
+        assert_eq!(world.read_resource::<Timer>().remaining, Duration::from_millis(750));
+    }
+}
@@ -0,0 +1,84 @@
+use specs::prelude::*;
+use sdl2::rect::Point;
+
+use crate::components::*;
+use super::DeltaTime;
+
+/// Returns the point a fraction `t` of the way from `from` to `to`
+pub fn lerp_point(from: Point, to: Point, t: f32) -> Point {
+    let x = from.x() as f32 + (to.x() - from.x()) as f32 * t;
+    let y = from.y() as f32 + (to.y() - from.y()) as f32 * t;
+    Point::new(x.round() as i32, y.round() as i32)
+}
+
+pub struct TweenSystem;
+
+impl<'a> System<'a> for TweenSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, DeltaTime>,
+        WriteStorage<'a, Tween>,
+        WriteStorage<'a, Position>,
+    );
+
+    fn run(&mut self, (entities, delta_time, mut tweens, mut positions): Self::SystemData) {
+        let mut finished = Vec::new();
+        for (entity, tween, position) in (&entities, &mut tweens, &mut positions).join() {
+            tween.elapsed = (tween.elapsed + delta_time.0).min(tween.duration);
+
+            let t = if tween.duration.is_zero() {
+                1.0
+            } else {
+                tween.elapsed.as_secs_f32() / tween.duration.as_secs_f32()
+            };
+            position.0 = lerp_point(tween.from, tween.to, tween.easing.apply(t));
+
+            if tween.elapsed >= tween.duration {
+                finished.push(entity);
+            }
+        }
+
+        // Can't remove components from the storage we're joining over, so do it afterwards
+        for entity in finished {
+            tweens.remove(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::easing::EasingKind;
+    use std::time::Duration;
+
+    #[test]
+    fn tween_is_halfway_at_its_midpoint_and_removed_at_the_end() {
+        let mut world = World::new();
+        world.register::<Tween>();
+        world.register::<Position>();
+        world.add_resource(DeltaTime(Duration::from_millis(500)));
+        let entity = world.create_entity()
+            .with(Position(Point::new(0, -400)))
+            .with(Tween {
+                from: Point::new(0, -400),
+                to: Point::new(100, 0),
+                duration: Duration::from_secs(1),
+                elapsed: Duration::default(),
+                easing: EasingKind::Linear,
+            })
+            .build();
+
+        TweenSystem.run_now(&world.res);
+        assert_eq!(world.read_storage::<Position>().get(entity).unwrap().0, Point::new(50, -200));
+        assert!(world.read_storage::<Tween>().contains(entity));
+
+        TweenSystem.run_now(&world.res);
+        assert_eq!(world.read_storage::<Position>().get(entity).unwrap().0, Point::new(100, 0));
+        assert!(!world.read_storage::<Tween>().contains(entity));
+    }
+
+    #[test]
+    fn lerp_point_rounds_to_the_nearest_pixel() {
+        assert_eq!(lerp_point(Point::new(0, 0), Point::new(10, -10), 0.25), Point::new(3, -3));
+    }
+}
@@ -0,0 +1,31 @@
+use specs::prelude::*;
+
+use crate::components::*;
+use crate::level::Level;
+
+/// Marks the enemies with a clear line of sight to the player as Alert
+pub struct Vision;
+
+impl<'a> System<'a> for Vision {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Alert>,
+        ReadExpect<'a, Level>,
+    );
+
+    fn run(&mut self, (entities, positions, enemies, players, mut alerts, level): Self::SystemData) {
+        let player_position = (&positions, &players).join().map(|(pos, _)| pos.0).next();
+
+        for (entity, pos, _) in (&entities, &positions, &enemies).join() {
+            let sees_player = player_position.is_some_and(|player| level.can_see(pos.0, player));
+            if sees_player {
+                alerts.insert(entity, Alert).expect("entity from join should be alive");
+            } else {
+                alerts.remove(entity);
+            }
+        }
+    }
+}